@@ -1,6 +1,4 @@
-use std::collections::HashSet;
-
-use indexmap::IndexSet;
+use std::{collections::HashSet, mem};
 
 use crate::{
     declarations::{Class, Function, FunctionKind, Param, Type},
@@ -8,7 +6,8 @@ use crate::{
 };
 
 use super::{
-    common::safe_name,
+    common::{analyze_deprecation, analyze_since, is_introspectable, safe_name},
+    functions::pair_async_functions,
     parser::{Event, TagResult},
     types::class_or_type_to_native,
     Analyzer,
@@ -20,21 +19,99 @@ const RECORD_TAG: &str = "record";
 
 const IMPLEMENTS_TAG: &str = "implements";
 
+const GTYPE_STRUCT_FOR_ATTR: &str = "is-gtype-struct-for";
+
+/// Replaces the invoker of every virtual method with the python name of the
+/// method, or removes it if the class doesn't have such method
+pub fn link_invokers(namespace: &str, class: &mut Class) {
+    let methods: HashSet<_> = class
+        .methods
+        .iter()
+        .filter(|m| m.kind != FunctionKind::Virtual)
+        .map(|m| m.name.clone())
+        .collect();
+
+    class.methods = mem::take(&mut class.methods)
+        .into_iter()
+        .map(|mut m| {
+            m.invoker = m
+                .invoker
+                .filter(|invoker| methods.contains(invoker))
+                .map(|invoker| format!("{}.{}.{}", namespace, class.name, invoker));
+            m
+        })
+        .collect();
+}
+
 impl Analyzer {
     fn try_an_implementor(&mut self, ev: &mut Event) -> TagResult<Type> {
         self.try_an_type_like_tag(IMPLEMENTS_TAG, ev)
     }
 
-    pub fn try_an_class(&mut self, ev: &mut Event) -> TagResult<(Class, HashSet<String>)> {
-        let (depth, attrs, ..) = tag_matches!(ev, CLASS_TAG, INTERFACE_TAG, RECORD_TAG);
+    /// Parses the class struct of a class, like `GtkWidgetClass`. It's only
+    /// used from C, but PyGObject exposes its methods as class methods of the
+    /// class it belongs to. Returns the name of the class and those methods
+    pub fn try_a_class_struct(&mut self, ev: &mut Event) -> TagResult<(String, Vec<Function>)> {
+        let (depth, attrs, ..) = tag_matches!(ev, RECORD_TAG);
+
+        let class = match attrs.get(GTYPE_STRUCT_FOR_ATTR) {
+            Some(class) => safe_name(class),
+            None => return Ok(None),
+        };
+        let name = safe_name(attrs.get_must("name")?);
+        let mut methods = Vec::new();
+
+        while ev.below(depth)? {
+            if let Some(mut m) = self.try_an_function(ev, Some(&name))? {
+                if matches!(m.kind, FunctionKind::Method) {
+                    m.kind = FunctionKind::ClassMethod;
+                }
+                methods.push(m);
+            }
+        }
+
+        self.log(format!(
+            "moved {} methods of {} to {} as class methods",
+            methods.len(),
+            name,
+            class
+        ));
+
+        Ok(Some((class, methods)))
+    }
+
+    pub fn try_an_class(
+        &mut self,
+        ev: &mut Event,
+        namespace: &str,
+    ) -> TagResult<(Class, HashSet<String>)> {
+        let (depth, attrs, tag) = tag_matches!(ev, CLASS_TAG, INTERFACE_TAG, RECORD_TAG);
+
+        if attrs.get(GTYPE_STRUCT_FOR_ATTR).is_some() {
+            // handled by try_a_class_struct
+            return Ok(None);
+        }
 
         let mut imports = HashSet::new();
 
         let name = safe_name(attrs.get_must("name")?);
+
+        if !is_introspectable(&attrs) {
+            return self.skip(ev, depth, &name, "not introspectable");
+        }
+        if tag == RECORD_TAG && name.ends_with("Private") {
+            return self.skip(ev, depth, &name, "private struct");
+        }
         let mut bases = Vec::new();
-        let mut fields = Vec::new();
-        let mut methods = IndexSet::new();
+        let fields = Vec::new();
+        let mut methods = Vec::new();
         let mut doc = None;
+        let mut deprecated = analyze_deprecation(&attrs);
+        let since = analyze_since(&attrs);
+        let c_name = attrs.get("type");
+        let mut source = None;
+        let is_abstract = attrs.get("abstract").as_deref() == Some("1");
+        let is_final = attrs.get("final").as_deref() == Some("1");
 
         if let Some(parent) = attrs.get("parent") {
             let typ = class_or_type_to_native(&parent);
@@ -50,13 +127,18 @@ impl Analyzer {
             if doc.is_none() {
                 doc = self.try_an_doc(ev)?;
             }
+            self.try_a_deprecation_doc(ev, depth, &mut deprecated)?;
+            self.try_a_source_position(ev, depth, &mut source)?;
 
             if let Some(f) = self.try_a_property(ev)? {
                 con_params.push(Param::Named {
                     name: f.name.clone(),
                     typ: f.typ.unwrap_or(Type::Any),
-                    doc: None,
+                    // keyword arguments can't be marked as deprecated, so
+                    // leave a note in the constructor docs instead
+                    doc: f.deprecated.map(|d| format!("Deprecated {}", d)),
                     optional: true,
+                    since: f.since.map(|v| (namespace.to_string(), v)),
                 });
                 // fields.push(f)
             }
@@ -67,10 +149,12 @@ impl Analyzer {
                 bases.push(i)
             }
             if let Some(m) = self.try_an_function(ev, Some(&name))? {
-                methods.insert(m);
+                methods.push(m);
             }
         }
 
+        pair_async_functions(&mut methods);
+
         if con_params.len() == 1 {
             con_params.clear();
         }
@@ -82,18 +166,32 @@ impl Analyzer {
             kind: FunctionKind::Method,
             return_doc: None,
             doc: None,
+            deprecated: None,
+            since: None,
+            c_name: None,
+            source: None,
+            finish: None,
+            invoker: None,
         };
 
-        return Ok(Some((
+        Ok(Some((
             Class {
                 name,
                 bases,
                 doc,
+                deprecated,
+                since,
+                c_name,
+                source,
+                is_abstract,
+                is_final,
                 fields,
                 constructor,
-                methods,
+                methods: methods.into_iter().collect(),
+                raw_doc: false,
+                snippets: Vec::new(),
             },
             imports,
-        )));
+        )))
     }
 }
@@ -1,10 +1,10 @@
 use crate::{
-    declarations::{Value, Var},
+    declarations::{Deprecation, SourcePosition, Type, Value, Var, Version},
     tag_matches,
 };
 
 use super::{
-    parser::{Event, TagResult, XmlEvent},
+    parser::{Attributes, Event, TagResult, XmlEvent},
     Analyzer,
 };
 use lazy_static::lazy_static;
@@ -12,19 +12,98 @@ use regex::Regex;
 use std::collections::HashSet;
 
 const DOC_TAG: &str = "doc";
+const DOC_DEPRECATED_TAG: &str = "doc-deprecated";
 const PROPERTY_TAG: &str = "property";
+const SOURCE_POSITION_TAG: &str = "source-position";
 
-pub fn analyze_value(val: &str) -> Value {
+/// Turns the value of a constant into a python literal of its type. Untyped
+/// values, like the ones of enum members, are integers
+pub fn analyze_value(val: &str, typ: Option<&Type>) -> Value {
     lazy_static! {
-        static ref INT: Regex = Regex::new("^[-+]?[0-9]+$").unwrap();
+        /// C integers, with an optional `U`, `L`, `UL`, `LL` or `ULL` suffix
+        static ref INT: Regex =
+            Regex::new("^(?P<sign>[-+]?)(?P<num>0[xX][0-9a-fA-F]+|[0-9]+)[uU]?[lL]{0,2}$").unwrap();
     }
-    if INT.is_match(val) {
-        Value::Number(val.to_string())
-    } else if val == "(null)" {
-        Value::None
-    } else {
-        Value::Str(val.to_string())
+    let int = || {
+        let c = INT.captures(val)?;
+        let num = &c["num"];
+        // C octals, like 0755
+        let num = match num.strip_prefix('0') {
+            Some(oct) if !oct.is_empty() && oct.bytes().all(|b| b.is_ascii_digit()) => {
+                if oct.bytes().any(|b| b > b'7') {
+                    return None;
+                }
+                format!("0o{}", oct)
+            }
+            _ => num.to_string(),
+        };
+        Some(Value::Int(format!("{}{}", &c["sign"], num)))
+    };
+    let float = || {
+        let num = val.trim_end_matches(['f', 'F']);
+        let parsed: f64 = num.parse().ok()?;
+        if !parsed.is_finite() {
+            return None;
+        }
+        if num.contains(['.', 'e', 'E']) {
+            Some(Value::Float(num.to_string()))
+        } else {
+            Some(Value::Float(format!("{}.0", num)))
+        }
+    };
+
+    let value = match typ {
+        Some(Type::Primitive(p)) => match p.as_str() {
+            "int" | "long" => int(),
+            "float" => float(),
+            "bool" => match val {
+                "true" | "TRUE" | "1" => Some(Value::Bool(true)),
+                "false" | "FALSE" | "0" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            "str" if val == "(null)" => Some(Value::None),
+            "str" => Some(Value::Str(val.to_string())),
+            _ => None,
+        },
+        None => int(),
+        _ => None,
+    };
+    value.unwrap_or(Value::Ellipsis)
+}
+
+/// Checks the `introspectable` attribute. Non introspectable declarations
+/// don't exist at runtime
+pub fn is_introspectable(attrs: &Attributes) -> bool {
+    attrs.get("introspectable").as_deref() != Some("0")
+}
+
+/// Reads the `deprecated` and `deprecated-version` attributes. Older GIR files
+/// may store the deprecation message itself in `deprecated`
+pub fn analyze_deprecation(attrs: &Attributes) -> Option<Deprecation> {
+    let version = attrs.get("deprecated-version");
+    let doc = match attrs.get("deprecated").as_deref() {
+        None | Some("0") if version.is_none() => return None,
+        None | Some("0") | Some("1") => None,
+        Some(message) => Some(message.to_string()),
+    };
+
+    Some(Deprecation { version, doc })
+}
+
+/// Reads the `version` attribute, the version in which the declaration was
+/// introduced
+pub fn analyze_since(attrs: &Attributes) -> Option<Version> {
+    attrs.get("version").and_then(|v| v.parse().ok())
+}
+
+/// Returns the first chunk of text found below `depth`
+fn text_below(ev: &mut Event, depth: usize) -> TagResult<String> {
+    while ev.below(depth)? {
+        if let XmlEvent::Characters(text) = &ev.event {
+            return Ok(Some(text.to_owned()));
+        }
     }
+    Ok(None)
 }
 
 pub fn safe_name(mut name: String) -> String {
@@ -53,36 +132,87 @@ impl Analyzer {
             return Ok(None);
         }
         let (depth, ..) = tag_matches!(ev, DOC_TAG);
-        while ev.below(depth)? {
-            if let XmlEvent::Characters(text) = &ev.event {
-                return Ok(Some(text.to_owned()));
-            }
+        text_below(ev, depth)
+    }
+
+    /// Parses a `<doc-deprecated />` placed directly below the tag at
+    /// `parent` depth, and stores its text in `deprecated`. Signals and other
+    /// children with their own deprecation notes are left alone
+    pub fn try_a_deprecation_doc(
+        &self,
+        ev: &mut Event,
+        parent: usize,
+        deprecated: &mut Option<Deprecation>,
+    ) -> TagResult<()> {
+        if ev.depth != parent + 1 {
+            return Ok(None);
         }
-        Ok(None)
+        let (depth, ..) = tag_matches!(ev, DOC_DEPRECATED_TAG);
+
+        if let Some(text) = text_below(ev, depth)? {
+            deprecated.get_or_insert_with(Deprecation::default).doc = Some(text);
+        }
+        Ok(Some(()))
+    }
+
+    /// Parses a `<source-position />` placed directly below the tag at
+    /// `parent` depth, and stores it in `source`
+    pub fn try_a_source_position(
+        &self,
+        ev: &mut Event,
+        parent: usize,
+        source: &mut Option<SourcePosition>,
+    ) -> TagResult<()> {
+        if ev.depth != parent + 1 {
+            return Ok(None);
+        }
+        let (_, attrs, ..) = tag_matches!(ev, SOURCE_POSITION_TAG);
+
+        *source = Some(SourcePosition {
+            filename: attrs.get_must("filename")?,
+            line: attrs.get("line"),
+        });
+        Ok(Some(()))
     }
 
     pub fn try_an_variable(&self, tag: &str, ev: &mut Event) -> TagResult<Var> {
         let (depth, attrs, ..) = tag_matches!(ev, tag);
 
         let name = attrs.get_must("name")?;
-        let value = attrs.get("value").map(|s| analyze_value(&s));
+        if !is_introspectable(&attrs) {
+            return self.skip(ev, depth, &name, "not introspectable");
+        }
+        let value = attrs.get("value");
         let mut typ = None;
         let mut doc = None;
+        let mut deprecated = analyze_deprecation(&attrs);
+        let since = analyze_since(&attrs);
+        // c:identifier for enum members, c:type for constants
+        let c_name = attrs.get("identifier").or_else(|| attrs.get("type"));
+        let mut source = None;
 
         while ev.below(depth)? {
             if doc.is_none() {
                 doc = self.try_an_doc(ev)?;
             }
+            self.try_a_deprecation_doc(ev, depth, &mut deprecated)?;
+            self.try_a_source_position(ev, depth, &mut source)?;
             if typ.is_none() {
                 typ = self.try_a_class_type(ev)?;
             }
         }
 
+        let value = value.map(|v| analyze_value(&v, typ.as_ref()));
+
         Ok(Some(Var {
             name,
             value,
             typ,
             doc,
+            deprecated,
+            since,
+            c_name,
+            source,
             constant: false,
         }))
     }
@@ -96,3 +226,75 @@ impl Analyzer {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The python literal of the value with the type
+    fn literal(val: &str, typ: &str) -> String {
+        analyze_value(val, Some(&Type::Primitive(typ.to_string()))).to_string()
+    }
+
+    #[test]
+    fn ints() {
+        assert_eq!(literal("42", "int"), "42");
+        assert_eq!(literal("-7", "int"), "-7");
+        assert_eq!(literal("0", "int"), "0");
+        assert_eq!(literal("0xFF", "int"), "0xFF");
+        assert_eq!(literal("-0x1a", "int"), "-0x1a");
+        assert_eq!(literal("0755", "int"), "0o755");
+        assert_eq!(literal("00", "int"), "0o0");
+        assert_eq!(literal("089", "int"), "...");
+        // untyped values are integers too
+        assert_eq!(analyze_value("12", None).to_string(), "12");
+    }
+
+    #[test]
+    fn suffixes() {
+        assert_eq!(literal("10U", "int"), "10");
+        assert_eq!(literal("10L", "long"), "10");
+        assert_eq!(literal("10ul", "int"), "10");
+        assert_eq!(literal("0x10ULL", "int"), "0x10");
+        assert_eq!(literal("10LLL", "int"), "...");
+        assert_eq!(literal("10UU", "int"), "...");
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(literal("3.14", "float"), "3.14");
+        assert_eq!(literal("2", "float"), "2.0");
+        assert_eq!(literal("1e-5", "float"), "1e-5");
+        assert_eq!(literal("0.5f", "float"), "0.5");
+        assert_eq!(literal("inf", "float"), "...");
+        assert_eq!(literal("NAN", "float"), "...");
+    }
+
+    #[test]
+    fn bools() {
+        assert_eq!(literal("true", "bool"), "True");
+        assert_eq!(literal("TRUE", "bool"), "True");
+        assert_eq!(literal("1", "bool"), "True");
+        assert_eq!(literal("false", "bool"), "False");
+        assert_eq!(literal("0", "bool"), "False");
+        assert_eq!(literal("yes", "bool"), "...");
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(literal("(null)", "str"), "None");
+        assert_eq!(literal("gtk", "str"), "\"gtk\"");
+        assert_eq!(literal("say \"hi\"", "str"), r#""say \"hi\"""#);
+        assert_eq!(literal(r"C:\path", "str"), r#""C:\\path""#);
+        assert_eq!(literal("a\nb", "str"), r#""a\nb""#);
+    }
+
+    #[test]
+    fn fallback() {
+        assert_eq!(literal("G_MAXINT", "int"), "...");
+        assert_eq!(literal("1.5", "int"), "...");
+        assert_eq!(literal("x", "Widget"), "...");
+        assert_eq!(analyze_value("x", Some(&Type::Any)).to_string(), "...");
+        assert_eq!(analyze_value("0x", None).to_string(), "...");
+    }
+}
@@ -4,7 +4,7 @@ use crate::{
 };
 
 use super::{
-    common::safe_name,
+    common::{analyze_deprecation, analyze_since, is_introspectable, safe_name},
     parser::{Event, TagResult},
     Analyzer,
 };
@@ -27,19 +27,36 @@ impl Analyzer {
         let (depth, attrs, ..) = tag_matches!(ev, ENUMERATION_TAG, BITFIELD_TAG);
 
         let name = attrs.get_must("name")?;
+        if !is_introspectable(&attrs) {
+            return self.skip(ev, depth, &name, "not introspectable");
+        }
         let mut values = Vec::new();
         let mut doc = None;
+        let mut deprecated = analyze_deprecation(&attrs);
+        let since = analyze_since(&attrs);
+        let c_name = attrs.get("type");
+        let mut source = None;
 
         while ev.below(depth)? {
             if doc.is_none() {
                 doc = self.try_an_doc(ev)?
             }
+            self.try_a_deprecation_doc(ev, depth, &mut deprecated)?;
+            self.try_a_source_position(ev, depth, &mut source)?;
             if let Some(mut member) = self.try_an_enum_bitfield_member(ev)? {
                 member.name = safe_name(member.name.to_uppercase());
                 values.push(member);
             }
         }
 
-        Ok(Some(Enumeration { name, doc, values }))
+        Ok(Some(Enumeration {
+            name,
+            doc,
+            deprecated,
+            since,
+            c_name,
+            source,
+            values,
+        }))
     }
 }
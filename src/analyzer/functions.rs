@@ -1,10 +1,12 @@
+use std::collections::HashMap;
+
 use crate::{
-    declarations::{Function, FunctionKind, Param, Type},
+    declarations::{Finish, Function, FunctionKind, Param, Type},
     tag_matches,
 };
 
 use super::{
-    common::safe_name,
+    common::{analyze_deprecation, analyze_since, is_introspectable, safe_name},
     parser::{Event, TagResult},
     Analyzer,
 };
@@ -20,6 +22,23 @@ const METHOD_TAG: &str = "method";
 const VIRTUAL_METHOD_TAG: &str = "virtual-method";
 const CONSTRUCTOR_TAG: &str = "constructor";
 
+/// Fills the result of every asynchronous function with the return type of its
+/// `*_finish` function. The ones without a finish function aren't asynchronous
+/// after all, so they lose the pair
+pub fn pair_async_functions(functions: &mut [Function]) {
+    let results: HashMap<_, _> = functions
+        .iter()
+        .map(|f| (f.name.clone(), f.return_type.clone()))
+        .collect();
+
+    for f in functions {
+        f.finish = f.finish.take().and_then(|mut finish| {
+            finish.result = results.get(&finish.name)?.clone();
+            Some(finish)
+        });
+    }
+}
+
 impl Analyzer {
     fn try_an_return_value(&mut self, ev: &mut Event) -> TagResult<(Option<String>, Type)> {
         let (depth, ..) = tag_matches!(ev, RETURN_VALUE_TAG);
@@ -75,6 +94,7 @@ impl Analyzer {
                 typ,
                 doc,
                 optional,
+                since: None,
             }))
         }
     }
@@ -92,9 +112,27 @@ impl Analyzer {
             CONSTRUCTOR_TAG
         );
 
-        let name = safe_name(attrs.get_must("name")?);
+        let mut name = safe_name(attrs.get_must("name")?);
+
+        // PyGObject only exposes the function that shadows another one, and
+        // it does so under the name of the shadowed function
+        if let Some(shadower) = attrs.get("shadowed-by") {
+            return self.skip(ev, depth, &name, format!("shadowed by {}", shadower));
+        }
+        if !is_introspectable(&attrs) {
+            return self.skip(ev, depth, &name, "not introspectable");
+        }
+        if let Some(shadowed) = attrs.get("shadows") {
+            self.log(format!("renamed {} to {}: shadows it", name, shadowed));
+            name = safe_name(shadowed);
+        }
+
         let mut parameters = Vec::new();
         let mut doc = None;
+        let mut deprecated = analyze_deprecation(&attrs);
+        let since = analyze_since(&attrs);
+        let c_name = attrs.get("identifier");
+        let mut source = None;
         let mut kind = match tag {
             FUNCTION_TAG => FunctionKind::Static,
             METHOD_TAG | CONSTRUCTOR_TAG => FunctionKind::Method,
@@ -103,17 +141,15 @@ impl Analyzer {
         };
         let mut return_doc = None;
         let mut return_type = Type::Any;
-
-        if let Some(class) = method_of {
-            if tag == CONSTRUCTOR_TAG {
-                return_type = Type::LocalClass(class.to_string())
-            }
-        }
+        // name and closure index of the `scope="async"` callback
+        let mut callback = None;
 
         while ev.below(depth)? {
             if doc.is_none() {
                 doc = self.try_an_doc(ev)?;
             }
+            self.try_a_deprecation_doc(ev, depth, &mut deprecated)?;
+            self.try_a_source_position(ev, depth, &mut source)?;
             if matches!(return_type, Type::Any) && return_doc.is_none() {
                 if let Some((rd, rt)) = self.try_an_return_value(ev)? {
                     return_doc = rd;
@@ -123,7 +159,15 @@ impl Analyzer {
 
             if let Some((depth, ..)) = ev.matches_tag([PARAMETERS_TAG]) {
                 while ev.below(depth)? {
+                    let closure = ev
+                        .check_start(PARAMETER_TAG)
+                        .filter(|attrs| attrs.get("scope").as_deref() == Some("async"))
+                        .map(|attrs| attrs.get("closure"));
+
                     if let Some(p) = self.try_an_param(ev)? {
+                        if let (Some(closure), Param::Named { name, .. }) = (closure, &p) {
+                            callback = Some((name.clone(), closure));
+                        }
                         parameters.push(p);
                     }
                 }
@@ -131,11 +175,7 @@ impl Analyzer {
         }
 
         // check if the method is static by checking if a self parameter exists
-        let first_param = if parameters.len() > 0 {
-            Some(&parameters[0])
-        } else {
-            None
-        };
+        let first_param = parameters.first();
 
         kind = match (kind, first_param) {
             (FunctionKind::Method, Some(Param::Instance)) => FunctionKind::Method,
@@ -143,13 +183,64 @@ impl Analyzer {
             (kind, _) => kind,
         };
 
-        return Ok(Some(Function {
+        // GIR files from gobject-introspection 1.80 and newer point to the
+        // finish function with glib:finish-func. they also have
+        // glib:sync-func and glib:async-func, which aren't needed to pair
+        // them. older ones only have the names, like `load_contents_async`
+        // and `load_contents_finish`, or `open` and `open_finish`. destroy
+        // notify callbacks are also async, but their functions have no finish
+        // pair, which pair_async_functions checks
+        let finish_name = attrs.get("finish-func").unwrap_or_else(|| {
+            let base = name.strip_suffix("_async").unwrap_or(&name);
+            format!("{}_finish", base)
+        });
+
+        let finish = match callback {
+            Some((callback, closure)) => {
+                // closure indexes don't count the instance parameter
+                let mut named = parameters.iter().filter_map(|p| match p {
+                    Param::Named { name, .. } | Param::Variadic { name, .. } => Some(name),
+                    _ => None,
+                });
+                let user_data = closure
+                    .and_then(|c| c.parse().ok())
+                    .and_then(|c: usize| named.nth(c));
+
+                Some(Finish {
+                    name: safe_name(finish_name),
+                    callback_params: std::iter::once(callback)
+                        .chain(user_data.cloned())
+                        .collect(),
+                    result: Type::Any,
+                })
+            }
+            None => None,
+        };
+
+        // constructors are class methods, so subclasses can use them too
+        if let (CONSTRUCTOR_TAG, Some(class)) = (tag, method_of) {
+            kind = FunctionKind::ClassMethod;
+            parameters.insert(0, Param::Instance);
+            return_type = match return_type {
+                Type::LocalClass(ret) if ret == class => Type::Instance,
+                // C constructors often return a base type, like GtkWidget
+                _ => Type::LocalClass(class.to_string()),
+            };
+        }
+
+        Ok(Some(Function {
             name,
             parameters,
             return_type,
             kind,
             return_doc,
             doc,
-        }));
+            deprecated,
+            since,
+            c_name,
+            source,
+            finish,
+            invoker: attrs.get("invoker").map(safe_name),
+        }))
     }
 }
@@ -0,0 +1,124 @@
+use indexmap::{IndexMap, IndexSet};
+
+use crate::declarations::{Class, Param, Type};
+
+use super::Analyzer;
+
+/// Name of the `typing.TypeVar` used by the generic declarations
+const TYPE_VAR: &str = "T";
+
+/// What a generic method returns, in terms of its `GType` parameter
+enum Returns {
+    /// an instance of the type, `T`
+    Instance,
+    /// an instance of the type or `None`, `typing.Optional[T]`
+    Nullable,
+    /// the (generic) class of the method holding items of the type, `Class[T]`
+    Container,
+}
+
+/// A method that takes a `GType` and returns something of that type. The
+/// `GType` parameter becomes a `type[T]`
+struct GenericMethod {
+    namespace: &'static str,
+    class: &'static str,
+    method: &'static str,
+    param: &'static str,
+    returns: Returns,
+}
+
+macro_rules! generic {
+    ($ns:ident.$class:ident.$method:ident($param:ident) -> $returns:ident) => {
+        GenericMethod {
+            namespace: stringify!($ns),
+            class: stringify!($class),
+            method: stringify!($method),
+            param: stringify!($param),
+            returns: Returns::$returns,
+        }
+    };
+}
+
+const GENERIC_METHODS: &[GenericMethod] = &[
+    generic!(GObject.Object.new(object_type) -> Instance),
+    generic!(GObject.Object.new_with_properties(object_type) -> Instance),
+    generic!(Gio.ListStore.new(item_type) -> Container),
+    generic!(Gtk.Widget.get_ancestor(widget_type) -> Nullable),
+    generic!(Gtk.Widget.get_template_child(widget_type) -> Instance),
+];
+
+/// Classes that are generic over the type of the items they hold
+const GENERIC_CLASSES: &[(&str, &str)] = &[("Gio", "ListModel"), ("Gio", "ListStore")];
+
+impl Analyzer {
+    /// Makes the methods listed in [`GENERIC_METHODS`] and the classes listed
+    /// in [`GENERIC_CLASSES`] generic, adding the type variable they use to
+    /// `type_vars`
+    pub fn mark_generics(
+        &self,
+        namespace: &str,
+        classes: &mut IndexMap<String, Class>,
+        type_vars: &mut IndexSet<String>,
+    ) {
+        let type_var = || Type::Var(TYPE_VAR.to_string());
+        let mut used = false;
+
+        let generic_classes: Vec<_> = GENERIC_CLASSES
+            .iter()
+            .filter(|(ns, _)| *ns == namespace)
+            .map(|&(_, name)| name)
+            .collect();
+
+        for &name in &generic_classes {
+            if let Some(class) = classes.get_mut(name) {
+                // the generic bases get the type variable, so `typing.Generic`
+                // is only needed when there are none
+                let mut inherited = false;
+                for base in &mut class.bases {
+                    if matches!(base, Type::LocalClass(b) if generic_classes.contains(&b.as_str()))
+                    {
+                        *base = Type::Generic(Box::new(base.clone()), vec![type_var()]);
+                        inherited = true;
+                    }
+                }
+                if !inherited {
+                    class.bases.push(Type::Generic(
+                        Box::new(Type::Primitive("typing.Generic".into())),
+                        vec![type_var()],
+                    ));
+                }
+                used = true;
+            }
+        }
+
+        for generic in GENERIC_METHODS.iter().filter(|g| g.namespace == namespace) {
+            let class = match classes.get_mut(generic.class) {
+                Some(class) => class,
+                None => continue,
+            };
+            let class_name = class.name.clone();
+
+            used |= class.transform_method(generic.method, |method| {
+                for param in &mut method.parameters {
+                    if let Param::Named { name, typ, .. } = param {
+                        if name == generic.param {
+                            *typ = Type::ClassOf(Box::new(type_var()));
+                        }
+                    }
+                }
+                method.return_type = match generic.returns {
+                    Returns::Instance => type_var(),
+                    Returns::Nullable => Type::Optional(Box::new(type_var())),
+                    Returns::Container => {
+                        Type::Generic(Box::new(Type::LocalClass(class_name)), vec![type_var()])
+                    }
+                };
+            });
+        }
+
+        if used {
+            self.log(format!("made generic declarations of {}", namespace));
+            type_vars.insert(TYPE_VAR.to_string());
+        }
+    }
+}
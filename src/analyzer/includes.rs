@@ -0,0 +1,292 @@
+//! Follows the `<include />` tags of the GIR files before any of them is
+//! analyzed, to find the versions of the namespaces that a run needs. Only one
+//! version of a namespace fits in the stubs, so when two are needed, like
+//! `Gtk-3.0` and the `Gtk-4.0` that `Adw-1` includes, `--prefer` picks one and
+//! what needs the other is left out
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    fs::File,
+    str::FromStr,
+};
+
+use xml::{reader::XmlEvent, EventReader};
+
+use super::{GIR_PATH, INCLUDE_TAG, NAMESPACE_TAG};
+
+/// A namespace and its version, like `Gtk-4.0`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Gir {
+    pub name: String,
+    pub version: String,
+}
+
+/// What's left to analyze after settling the conflicts
+pub struct Resolution {
+    /// the GIRs to analyze, in the order they were asked for
+    pub roots: Vec<Gir>,
+    /// the GIRs left out, with the version that lost which they need
+    pub left_out: Vec<(Gir, Gir)>,
+}
+
+impl fmt::Display for Gir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.name, self.version)
+    }
+}
+
+impl FromStr for Gir {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('-') {
+            Some((name, version)) if !name.is_empty() && !version.is_empty() => Ok(Self {
+                name: name.into(),
+                version: version.into(),
+            }),
+            _ => Err(format!(
+                "expected NAME-VERSION, like Gtk-4.0, found `{}`",
+                s
+            )),
+        }
+    }
+}
+
+/// Reads the `<include />` tags of a GIR file, which come before the
+/// namespace
+pub fn read_includes(gir: &Gir) -> Result<Vec<Gir>, String> {
+    let path = format!("{}{}.gir", GIR_PATH, gir);
+    let file = File::open(&path).map_err(|e| format!("{}: {}", path, e))?;
+    let mut includes = Vec::new();
+
+    for event in EventReader::new(file) {
+        match event.map_err(|e| format!("{}: {}", path, e))? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => {
+                if name.local_name == NAMESPACE_TAG {
+                    break;
+                }
+                if name.local_name != INCLUDE_TAG {
+                    continue;
+                }
+                let attr = |key: &str| {
+                    attributes
+                        .iter()
+                        .find(|a| a.name.local_name == key)
+                        .map(|a| a.value.clone())
+                };
+                if let (Some(name), Some(version)) = (attr("name"), attr("version")) {
+                    includes.push(Gir { name, version });
+                }
+            }
+            XmlEvent::EndDocument => break,
+            _ => {}
+        }
+    }
+
+    Ok(includes)
+}
+
+/// Whether the GIR is left out, because it lost or needs one that lost.
+/// Returns the one that lost
+fn lost<'a>(
+    gir: &'a Gir,
+    graph: &'a BTreeMap<Gir, Vec<Gir>>,
+    losers: &BTreeSet<&Gir>,
+) -> Option<&'a Gir> {
+    if losers.contains(gir) {
+        return Some(gir);
+    }
+    graph[gir]
+        .iter()
+        .find_map(|include| lost(include, graph, losers))
+}
+
+/// Finds the GIRs needed by `roots`, and checks that they need a single
+/// version of each namespace. Conflicts are settled by the versions in
+/// `prefer`, or reported. `includes` gives the GIRs that one includes, like
+/// [`read_includes`]
+pub fn resolve_versions(
+    roots: &[Gir],
+    prefer: &[Gir],
+    mut includes: impl FnMut(&Gir) -> Result<Vec<Gir>, String>,
+) -> Result<Resolution, String> {
+    let mut graph = BTreeMap::new();
+    // versions of each namespace, and what needs them
+    let mut required: BTreeMap<String, BTreeMap<String, Vec<String>>> = BTreeMap::new();
+
+    for root in roots {
+        required
+            .entry(root.name.clone())
+            .or_default()
+            .entry(root.version.clone())
+            .or_default()
+            .push("the command line".into());
+    }
+
+    let mut pending = roots.to_vec();
+    while let Some(gir) = pending.pop() {
+        if graph.contains_key(&gir) {
+            continue;
+        }
+        let includes = includes(&gir)?;
+        for include in &includes {
+            required
+                .entry(include.name.clone())
+                .or_default()
+                .entry(include.version.clone())
+                .or_default()
+                .push(gir.to_string());
+        }
+        pending.extend(includes.iter().cloned());
+        graph.insert(gir, includes);
+    }
+
+    let mut losers = BTreeSet::new();
+    let mut conflicts = Vec::new();
+
+    for (name, versions) in required.iter().filter(|(_, v)| v.len() > 1) {
+        let gir = |version: &String| Gir {
+            name: name.clone(),
+            version: version.clone(),
+        };
+
+        match prefer.iter().find(|p| p.name == *name) {
+            Some(preferred) if !versions.contains_key(&preferred.version) => {
+                let needed: Vec<_> = versions.keys().map(|v| gir(v).to_string()).collect();
+                conflicts.push(format!(
+                    "--prefer {} doesn't match the versions needed: {}",
+                    preferred,
+                    needed.join(", ")
+                ));
+            }
+            Some(preferred) => losers.extend(
+                versions
+                    .keys()
+                    .filter(|v| **v != preferred.version)
+                    .map(gir),
+            ),
+            None => {
+                let mut conflict = format!("conflicting versions of {}:", name);
+                for (version, by) in versions {
+                    conflict += &format!("\n  {} is needed by {}", gir(version), by.join(", "));
+                }
+                let options: Vec<_> = versions
+                    .keys()
+                    .map(|v| format!("--prefer {}", gir(v)))
+                    .collect();
+                conflict += &format!("\npick one with {}", options.join(" or "));
+                conflicts.push(conflict);
+            }
+        }
+    }
+
+    // a preference that settles nothing is likely a typo
+    for preferred in prefer {
+        if required.get(&preferred.name).is_none_or(|v| v.len() < 2) {
+            conflicts.push(format!(
+                "--prefer {}: there are no conflicting versions of {}",
+                preferred, preferred.name
+            ));
+        }
+    }
+
+    if !conflicts.is_empty() {
+        return Err(conflicts.join("\n"));
+    }
+
+    let losers: BTreeSet<_> = losers.iter().collect();
+    let mut resolution = Resolution {
+        roots: Vec::new(),
+        left_out: Vec::new(),
+    };
+
+    for gir in graph.keys() {
+        if let Some(loser) = lost(gir, &graph, &losers) {
+            resolution.left_out.push((gir.clone(), loser.clone()));
+        }
+    }
+    for root in roots {
+        let left_out = resolution.left_out.iter().any(|(gir, _)| gir == root);
+        if !left_out && !resolution.roots.contains(root) {
+            resolution.roots.push(root.clone());
+        }
+    }
+
+    Ok(resolution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gir(s: &str) -> Gir {
+        s.parse().unwrap()
+    }
+
+    /// Resolves with the includes of the map instead of the GIR files
+    fn resolve(roots: &[&str], prefer: &[&str]) -> Result<Resolution, String> {
+        let graph: BTreeMap<Gir, Vec<Gir>> = [
+            ("Adw-1", vec!["Gtk-4.0", "GObject-2.0"]),
+            ("Gtk-4.0", vec!["GObject-2.0"]),
+            ("Gtk-3.0", vec!["GObject-2.0"]),
+            ("GObject-2.0", vec![]),
+        ]
+        .into_iter()
+        .map(|(g, includes)| (gir(g), includes.into_iter().map(gir).collect()))
+        .collect();
+
+        let roots: Vec<_> = roots.iter().map(|r| gir(r)).collect();
+        let prefer: Vec<_> = prefer.iter().map(|p| gir(p)).collect();
+        resolve_versions(&roots, &prefer, |g| Ok(graph[g].clone()))
+    }
+
+    #[test]
+    fn no_conflict() {
+        let resolution = resolve(&["Adw-1", "Gtk-4.0"], &[]).unwrap();
+        assert_eq!(resolution.roots, [gir("Adw-1"), gir("Gtk-4.0")]);
+        assert!(resolution.left_out.is_empty());
+    }
+
+    #[test]
+    fn conflict_without_prefer() {
+        let err = resolve(&["Adw-1", "Gtk-3.0"], &[]).err().unwrap();
+        assert!(err.contains("conflicting versions of Gtk"));
+        assert!(err.contains("Gtk-4.0 is needed by Adw-1"));
+        assert!(err.contains("--prefer Gtk-3.0 or --prefer Gtk-4.0"));
+    }
+
+    #[test]
+    fn conflict_with_prefer() {
+        let resolution = resolve(&["Gtk-4.0", "Gtk-3.0"], &["Gtk-4.0"]).unwrap();
+        assert_eq!(resolution.roots, [gir("Gtk-4.0")]);
+        assert_eq!(resolution.left_out, [(gir("Gtk-3.0"), gir("Gtk-3.0"))]);
+    }
+
+    #[test]
+    fn transitive_loser() {
+        let resolution = resolve(&["Adw-1", "Gtk-3.0"], &["Gtk-3.0"]).unwrap();
+        assert_eq!(resolution.roots, [gir("Gtk-3.0")]);
+        assert_eq!(
+            resolution.left_out,
+            [
+                (gir("Adw-1"), gir("Gtk-4.0")),
+                (gir("Gtk-4.0"), gir("Gtk-4.0"))
+            ]
+        );
+    }
+
+    #[test]
+    fn prefer_without_conflict() {
+        let err = resolve(&["Adw-1"], &["Gtk-4.0"]).err().unwrap();
+        assert!(err.contains("--prefer Gtk-4.0: there are no conflicting versions of Gtk"));
+    }
+
+    #[test]
+    fn prefer_of_another_version() {
+        let err = resolve(&["Adw-1", "Gtk-3.0"], &["Gtk-5.0"]).err().unwrap();
+        assert!(err.contains("--prefer Gtk-5.0 doesn't match the versions needed"));
+    }
+}
@@ -2,29 +2,35 @@ mod classes;
 mod common;
 mod enumerations;
 mod functions;
+mod generics;
+mod includes;
 mod parser;
 mod types;
 
 use indexmap::{IndexMap, IndexSet};
 use std::{
-    collections::HashSet,
-    fmt::format,
-    fs::File,
-    io::{BufReader, Read},
-    mem,
-    path::{Path, PathBuf},
+    collections::{BTreeMap, HashSet},
+    fs::{self, File},
+    io::Read,
 };
 
-use xml::{EventReader, ParserConfig};
+use xml::{common::Position, EventReader, ParserConfig};
 
 use crate::{
-    declarations::{Class, Namespace, Param, Type, Var},
+    declarations::{Class, Namespace, Param, Type, Var, Version},
+    docs::Markup,
     tag_matches,
 };
 
+pub use self::{
+    classes::link_invokers,
+    includes::{read_includes, resolve_versions, Gir, Resolution},
+};
+
 use self::{
     common::safe_name,
-    parser::{Event, TagResult},
+    functions::pair_async_functions,
+    parser::{Error, Event, TagResult},
 };
 
 const GIR_PATH: &str = "/usr/share/gir-1.0/";
@@ -32,17 +38,58 @@ const INCLUDE_TAG: &str = "include";
 const REPOSITORY_TAG: &str = "repository";
 const NAMESPACE_TAG: &str = "namespace";
 const CONSTANT_TAG: &str = "constant";
+const DOC_FORMAT_TAG: &str = "format";
 
 /// Analyzes a gir document
 pub struct Analyzer {
     ignore_docs: bool,
+    verbose: bool,
     pub depth: usize,
     pub namespaces: IndexSet<Namespace>,
 }
 
+/// Name of a base class from this namespace, also when it's generic
+fn local_base(base: &Type) -> Option<&str> {
+    match base {
+        Type::LocalClass(name) => Some(name),
+        Type::Generic(base, _) => local_base(base),
+        _ => None,
+    }
+}
+
+/// The versions of each namespace with a GIR file that can be analyzed, like
+/// `3.0` and `4.0` for `Gtk`
+pub fn installed_girs() -> BTreeMap<String, Vec<String>> {
+    let mut installed: BTreeMap<_, Vec<_>> = BTreeMap::new();
+
+    let entries = fs::read_dir(GIR_PATH).into_iter().flatten().flatten();
+    for entry in entries {
+        let file_name = entry.file_name();
+        let gir = file_name.to_string_lossy();
+        if let Some((name, version)) = gir.strip_suffix(".gir").and_then(|g| g.rsplit_once('-')) {
+            installed
+                .entry(name.to_string())
+                .or_default()
+                .push(version.to_string());
+        }
+    }
+    for versions in installed.values_mut() {
+        versions.sort_by_cached_key(|v| v.parse::<Version>().ok());
+    }
+
+    installed
+}
+
+/// Analyzes a GIR document written in a test, which can't include others
+#[cfg(test)]
+pub fn analyze_gir(gir: &str) -> Namespace {
+    let source = std::io::Cursor::new(gir.to_string());
+    Analyzer::new(false, false).analyze(source).unwrap()
+}
+
 fn traverse(h: &mut IndexMap<String, Class>, s: &mut IndexSet<Class>, c: Class) {
     for base in &c.bases {
-        if let Type::LocalClass(base) = base {
+        if let Some(base) = local_base(base) {
             if let Some(base) = h.remove(base) {
                 traverse(h, s, base);
             }
@@ -52,26 +99,59 @@ fn traverse(h: &mut IndexMap<String, Class>, s: &mut IndexSet<Class>, c: Class)
 }
 
 impl Analyzer {
-    pub fn new(ignore_docs: bool) -> Self {
+    pub fn new(ignore_docs: bool, verbose: bool) -> Self {
         Self {
             ignore_docs,
+            verbose,
             depth: 0,
             namespaces: IndexSet::new(),
         }
     }
 
-    pub fn analyze_repository(&mut self, module: &str, version: &str) {
+    pub fn analyze_repository(&mut self, module: &str, version: &str) -> Result<(), String> {
+        // it may have been included by another one, but only one version of
+        // a namespace fits in the stubs
+        if let Some(ns) = self.namespaces.get(module) {
+            return match &ns.version {
+                Some(v) if version.parse().ok().as_ref() != Some(v) => Err(format!(
+                    "{}-{} is needed, but {}-{} was already analyzed",
+                    module, version, module, v
+                )),
+                _ => Ok(()),
+            };
+        }
         eprintln!("{}{} v{}", &"| ".repeat(self.depth), module, version);
         self.depth += 1;
-        let fname = format!("{}-{}.gir", module, version);
-        let gir = File::open(GIR_PATH.to_string() + &fname).unwrap();
-        // let file = BufReader::new(file);
+        let path = format!("{}{}-{}.gir", GIR_PATH, module, version);
+        let gir = File::open(&path).map_err(|e| format!("{}: {}", path, e))?;
 
-        let ns = self.analyze(gir);
+        let ns = self.analyze(gir).map_err(|e| format!("{}: {}", path, e));
         self.depth -= 1;
 
-        self.namespaces.insert(ns);
-        // let mut analyzer = Analyzer::new(no_docs);
+        self.namespaces.insert(ns?);
+        Ok(())
+    }
+
+    /// Prints a message below the current repository in verbose mode
+    pub fn log(&self, message: impl std::fmt::Display) {
+        if self.verbose {
+            eprintln!("{}- {}", &"| ".repeat(self.depth), message);
+        }
+    }
+
+    /// Consumes the rest of the tag at `depth`, leaving the declaration out of
+    /// the stubs. The reason is logged in verbose mode
+    pub fn skip<T>(
+        &self,
+        ev: &mut Event,
+        depth: usize,
+        name: &str,
+        reason: impl std::fmt::Display,
+    ) -> TagResult<T> {
+        while ev.below(depth)? {}
+
+        self.log(format!("removed {}: {}", name, reason));
+        Ok(None)
     }
 
     fn try_an_constant(&self, ev: &mut Event) -> TagResult<Var> {
@@ -89,16 +169,20 @@ impl Analyzer {
 
         let mut imports = HashSet::new();
         let mut namespace = None;
+        let mut doc_markup = Markup::default();
 
         while ev.below(depth)? {
+            if let Some(markup) = self.try_a_doc_format(ev)? {
+                doc_markup = markup;
+            }
             if let Some((name, version)) = self.try_an_include(ev)? {
-                if !self.namespaces.contains(name.as_str()) {
-                    self.analyze_repository(&name, &version);
-                }
+                self.analyze_repository(&name, &version)
+                    .map_err(|e| Error::include(e, ev.tree.position()))?;
                 imports.insert(name);
             }
             if let Some(mut ns) = self.try_a_namespace(ev)? {
                 ns.imports.extend(imports.drain());
+                ns.doc_markup = doc_markup;
                 namespace = Some(ns)
             }
         }
@@ -110,11 +194,21 @@ impl Analyzer {
         let (depth, attrs, ..) = tag_matches!(ev, NAMESPACE_TAG);
 
         let name = attrs.get_must("name")?;
+        let version = attrs.get("version").and_then(|v| v.parse().ok());
+        let prefixes = |attr| {
+            attrs
+                .get(attr)
+                .map(|p| p.split(',').map(String::from).collect())
+                .unwrap_or_default()
+        };
+        let c_identifier_prefixes = prefixes("identifier-prefixes");
+        let c_symbol_prefixes = prefixes("symbol-prefixes");
         let mut imports = HashSet::new();
         let mut constants = Vec::new();
         let mut enums = Vec::new();
         let mut functions = Vec::new();
         let mut classes = IndexMap::<String, Class>::new();
+        let mut class_structs = Vec::new();
 
         // Add constructor parameters from parent class
         // TODO: Add connect signals too
@@ -129,12 +223,40 @@ impl Analyzer {
             if let Some(f) = self.try_an_function(ev, None)? {
                 functions.push(f);
             }
-            if let Some((c, i)) = self.try_an_class(ev)? {
+            if let Some(s) = self.try_a_class_struct(ev)? {
+                class_structs.push(s);
+            }
+            if let Some((c, i)) = self.try_an_class(ev, &name)? {
                 imports.extend(i);
                 classes.insert(c.name.clone(), c);
             }
         }
 
+        pair_async_functions(&mut functions);
+
+        let mut type_vars = IndexSet::new();
+        self.mark_generics(&name, &mut classes, &mut type_vars);
+
+        for (class, methods) in class_structs {
+            if let Some(class) = classes.get_mut(&class) {
+                for method in methods {
+                    // a python class can't have two methods with the same
+                    // name, the one of the instance is kept
+                    if class.find_method(&method.name).is_some() {
+                        self.log(format!(
+                            "removed class method {}.{}: a method has the same name",
+                            class.name, method.name
+                        ));
+                        continue;
+                    }
+                    class.methods.insert(method);
+                }
+            }
+        }
+        for class in classes.values_mut() {
+            link_invokers(&name, class);
+        }
+
         // store the keys
         let keys: Vec<_> = classes.keys().cloned().collect();
 
@@ -148,10 +270,8 @@ impl Analyzer {
                         Type::ExternalClass { module, name } => self
                             .namespaces
                             .get(module.as_str())
-                            .map(|m| m.classes.get(name.as_str()))
-                            .flatten(),
-                        Type::LocalClass(name) => classes.get(name.as_str()),
-                        _ => None,
+                            .and_then(|m| m.classes.get(name.as_str())),
+                        base => local_base(base).and_then(|name| classes.get(name)),
                     };
 
                     if let Some(r) = resolved {
@@ -192,11 +312,19 @@ impl Analyzer {
 
         Ok(Some(Namespace {
             name,
+            version,
             imports,
+            type_vars,
+            doc_markup: Markup::default(),
+            c_identifier_prefixes,
+            c_symbol_prefixes,
+            modules: HashSet::new(),
+            extra_imports: IndexSet::new(),
             constants,
             enums,
             functions,
             classes: sorted_classes,
+            snippets: Vec::new(),
         }))
     }
 
@@ -207,13 +335,20 @@ impl Analyzer {
         Ok(version.map(|ver| (name, ver)))
     }
 
+    /// Parses the `<doc:format />` tag, which tells the markup used by the
+    /// documentation
+    fn try_a_doc_format(&mut self, ev: &mut Event) -> TagResult<Markup> {
+        let (_, attrs, ..) = tag_matches!(ev, DOC_FORMAT_TAG);
+        Ok(attrs.get("name").map(|name| Markup::from_name(&name)))
+    }
+
     /// Parses and consumes the source, returns the resulting [`Namespace`]
-    pub fn analyze(&mut self, source: impl Read + 'static) -> Namespace {
+    pub fn analyze(&mut self, source: impl Read + 'static) -> Result<Namespace, String> {
         let config = ParserConfig::new().trim_whitespace(true);
         let tree = EventReader::new_with_config(Box::new(source) as Box<dyn Read>, config);
 
         Event::consume(tree, |e| self.try_a_repository(e))
-            .unwrap()
-            .unwrap()
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "no <namespace /> found".to_string())
     }
 }
@@ -11,6 +11,8 @@ use xml::{
 pub enum ErrorKind {
     Xml(xml::reader::Error),
     MissingAttribute(String),
+    /// an included GIR that couldn't be analyzed
+    Include(String),
 }
 #[derive(Debug)]
 pub struct Error {
@@ -24,6 +26,24 @@ impl Error {
             kind: ErrorKind::MissingAttribute(attr.into()),
         }
     }
+
+    pub fn include(message: String, position: TextPosition) -> Self {
+        Self {
+            pos: position,
+            kind: ErrorKind::Include(message),
+        }
+    }
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            ErrorKind::Xml(err) => write!(f, "{}", err),
+            ErrorKind::MissingAttribute(attr) => {
+                write!(f, "{} missing attribute `{}`", self.pos, attr)
+            }
+            ErrorKind::Include(message) => write!(f, "{} {}", self.pos, message),
+        }
+    }
 }
 impl From<xml::reader::Error> for Error {
     fn from(err: xml::reader::Error) -> Self {
@@ -43,8 +63,8 @@ impl Attributes {
     pub fn get(&self, name: &str) -> Option<String> {
         self.0
             .iter()
-            .find(|attr| attr.name.local_name == String::from(name))
-            .and_then(|attr| Some(attr.value.to_owned()))
+            .find(|attr| attr.name.local_name == name)
+            .map(|attr| attr.value.to_owned())
     }
 
     pub fn get_must(&self, name: &str) -> Result<String> {
@@ -109,14 +129,14 @@ impl Event {
         F: FnMut(&mut Event) -> TagResult<T>,
     {
         let xml_event = tree.next()?;
-        let mut ev = &mut Event {
+        let ev = &mut Event {
             event: xml_event,
             tree,
             depth: 1,
         };
 
         while ev.below(1)? {
-            if let Some(v) = func(&mut ev)? {
+            if let Some(v) = func(ev)? {
                 return Ok(Some(v));
             }
         }
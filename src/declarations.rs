@@ -1,20 +1,62 @@
-use std::{borrow::Borrow, collections::HashSet, hash::Hash};
+use std::{borrow::Borrow, cmp::Ordering, collections::HashSet, hash::Hash, mem, str::FromStr};
 
+use if_chain::if_chain;
 use indexmap::IndexSet;
 
+use crate::{docs::Markup, generation::quote};
+
 #[derive(Debug, Clone)]
 pub enum Type {
     Primitive(String),
     LocalClass(String),
-    ExternalClass { module: String, name: String },
+    ExternalClass {
+        module: String,
+        name: String,
+    },
+    /// the class a method is called on, `typing.Self`
+    Instance,
+    /// a `typing.TypeVar` declared in the namespace
+    Var(String),
+    /// the class object itself, `type[T]`
+    ClassOf(Box<Type>),
+    /// `typing.Optional[T]`
+    Optional(Box<Type>),
+    /// a generic class with its type arguments, `Class[A, B]`
+    Generic(Box<Type>, Vec<Type>),
     Any,
 }
 
 #[derive(Debug, Clone)]
 pub enum Value {
-    Number(String),
+    /// a python int literal
+    Int(String),
+    /// a python float literal
+    Float(String),
+    Bool(bool),
     Str(String),
     None,
+    /// a value that can't be written as a python literal, `...`
+    Ellipsis,
+}
+
+/// A dotted version number, like `4.10` or `3.8`. Missing components compare
+/// as zero, so `4` and `4.0` are the same version
+#[derive(Debug, Clone)]
+pub struct Version(pub Vec<u32>);
+
+/// Deprecation info of a declaration, taken from the `deprecated` and
+/// `deprecated-version` attributes and the `<doc-deprecated />` tag
+#[derive(Debug, Clone, Default)]
+pub struct Deprecation {
+    pub version: Option<String>,
+    pub doc: Option<String>,
+}
+
+/// Where a declaration is found in the C headers, from `<source-position />`
+#[derive(Debug, Clone)]
+pub struct SourcePosition {
+    pub filename: String,
+    pub line: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -23,6 +65,12 @@ pub struct Var {
     pub value: Option<Value>,
     pub typ: Option<Type>,
     pub doc: Option<String>,
+    pub deprecated: Option<Deprecation>,
+    /// Version of the library that introduced it
+    pub since: Option<Version>,
+    /// name of the C symbol, `c:identifier` or `c:type`
+    pub c_name: Option<String>,
+    pub source: Option<SourcePosition>,
     pub constant: bool,
 }
 
@@ -31,6 +79,10 @@ pub struct Enumeration {
     pub name: String,
     pub values: Vec<Var>,
     pub doc: Option<String>,
+    pub deprecated: Option<Deprecation>,
+    pub since: Option<Version>,
+    pub c_name: Option<String>,
+    pub source: Option<SourcePosition>,
 }
 
 #[derive(Debug, Clone)]
@@ -41,6 +93,9 @@ pub enum Param {
         typ: Type,
         doc: Option<String>,
         optional: bool,
+        /// namespace and version that introduced it, for the keyword
+        /// arguments taken from properties, which subclasses inherit
+        since: Option<(String, Version)>,
     },
     /// `*args`. these ones cannot be optional
     Variadic {
@@ -48,20 +103,41 @@ pub enum Param {
         typ: Type,
         doc: Option<String>,
     },
-    /// in python would be `self`. not called `Self` 'cuz that's a keyword
+    /// `**kwargs`, the keyword arguments not taken by the other parameters
+    Keywords {
+        name: String,
+        typ: Type,
+        doc: Option<String>,
+    },
+    /// in python would be `self`, or `cls` for class methods. not called
+    /// `Self` 'cuz that's a keyword
     Instance,
     /// the `*` that makes the following parameters keyword only
     Star,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum FunctionKind {
     Static,
     Method,
     StaticMethod,
+    /// a method of the class struct, like `Gtk.WidgetClass.set_css_name`
+    ClassMethod,
     Virtual,
 }
 
+/// Links an asynchronous function to its `*_finish` function. When called
+/// without a callback, PyGObject returns an awaitable of the finish result
+#[derive(Debug, Clone)]
+pub struct Finish {
+    /// name of the `*_finish` function
+    pub name: String,
+    /// parameters only used when called with a callback, like `user_data`
+    pub callback_params: Vec<String>,
+    /// return type of the `*_finish` function
+    pub result: Type,
+}
+
 #[derive(Debug, Clone)]
 pub struct Function {
     pub name: String,
@@ -70,6 +146,15 @@ pub struct Function {
     pub kind: FunctionKind,
     pub return_doc: Option<String>,
     pub doc: Option<String>,
+    pub deprecated: Option<Deprecation>,
+    pub since: Option<Version>,
+    pub c_name: Option<String>,
+    pub source: Option<SourcePosition>,
+    pub finish: Option<Finish>,
+    /// method that calls this virtual method, from the `invoker` attribute.
+    /// [`link_invokers`](crate::analyzer::link_invokers) turns it into its
+    /// python name, like `Gtk.Widget.snapshot`
+    pub invoker: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -80,33 +165,182 @@ pub struct Class {
     pub methods: IndexSet<Function>,
     pub constructor: Function,
     pub doc: Option<String>,
+    pub deprecated: Option<Deprecation>,
+    pub since: Option<Version>,
+    pub c_name: Option<String>,
+    pub source: Option<SourcePosition>,
+    /// can't be instantiated, only subclassed
+    pub is_abstract: bool,
+    /// can't be subclassed
+    pub is_final: bool,
+    /// the docstring comes from python stubs, so it isn't converted
+    pub raw_doc: bool,
+    /// members from the extra stubs
+    pub snippets: Vec<Snippet>,
+}
+
+/// Python source copied as is from the extra stubs
+#[derive(Debug, Clone)]
+pub struct Snippet {
+    /// name of the declaration, empty for other statements
+    pub name: String,
+    pub source: String,
 }
 
 /// Contains all the declarations inside a `<namespace />`
 pub struct Namespace {
     pub name: String,
+    /// version of the API, like `4.0`
+    pub version: Option<Version>,
     pub imports: HashSet<String>,
+    /// names of the `typing.TypeVar`s used by generic declarations
+    pub type_vars: IndexSet<String>,
+    /// markup of the documentation, from `<doc:format />`
+    pub doc_markup: Markup,
+    /// prefixes of the C types, `c:identifier-prefixes`
+    pub c_identifier_prefixes: Vec<String>,
+    /// prefixes of the C functions, `c:symbol-prefixes`
+    pub c_symbol_prefixes: Vec<String>,
+    /// python modules imported as is, like `cairo`
+    pub modules: HashSet<String>,
+    /// other import statements, from the extra stubs
+    pub extra_imports: IndexSet<String>,
     pub constants: Vec<Var>,
     pub enums: Vec<Enumeration>,
     pub functions: Vec<Function>,
     pub classes: IndexSet<Class>,
+    /// declarations from the extra stubs
+    pub snippets: Vec<Snippet>,
+}
+
+impl Type {
+    /// Calls `f` on the types inside this one, and then on itself
+    pub fn visit(&mut self, f: &mut impl FnMut(&mut Type)) {
+        match self {
+            Self::ClassOf(typ) | Self::Optional(typ) => typ.visit(f),
+            Self::Generic(typ, args) => {
+                typ.visit(f);
+                args.iter_mut().for_each(|a| a.visit(f));
+            }
+            _ => {}
+        }
+        f(self)
+    }
+}
+
+impl Function {
+    /// Functions are keyed by name and kind, so virtual methods don't collide
+    /// with the methods that call them
+    pub fn key(&self) -> (&str, FunctionKind) {
+        (&self.name, self.kind)
+    }
+
+    /// Calls `f` on the types of the parameters and the return value
+    pub fn for_each_type(&mut self, f: &mut impl FnMut(&mut Type)) {
+        for p in &mut self.parameters {
+            if let Param::Named { typ, .. }
+            | Param::Variadic { typ, .. }
+            | Param::Keywords { typ, .. } = p
+            {
+                typ.visit(f);
+            }
+        }
+        self.return_type.visit(f);
+        if let Some(finish) = &mut self.finish {
+            finish.result.visit(f);
+        }
+    }
+}
+
+impl Class {
+    /// Returns the index of the method called `name`, virtual methods aside
+    pub fn find_method(&self, name: &str) -> Option<usize> {
+        self.methods
+            .iter()
+            .position(|m| m.name == name && m.kind != FunctionKind::Virtual)
+    }
+
+    /// Applies `tfn` to the method called `name`, keeping the order of the
+    /// methods. Returns `false` if there's no such method
+    pub fn transform_method(&mut self, name: &str, tfn: impl FnOnce(&mut Function)) -> bool {
+        let origin = self.find_method(name);
+        let methods = &mut self.methods; // alias
+
+        if_chain! {
+            if let Some(origin) = origin;
+            if let Some(mut method) = methods.swap_remove_index(origin);
+            then {
+                tfn(&mut method);
+                methods.insert(method);
+                methods.swap_indices(methods.len() - 1, origin);
+                true
+            } else {
+                false
+            }
+        }
+    }
+}
+
+impl Namespace {
+    /// Calls `f` on every type used by the declarations of the namespace
+    pub fn for_each_type(&mut self, f: &mut impl FnMut(&mut Type)) {
+        let vars = self
+            .constants
+            .iter_mut()
+            .chain(self.enums.iter_mut().flat_map(|e| e.values.iter_mut()));
+        for typ in vars.filter_map(|v| v.typ.as_mut()) {
+            typ.visit(f);
+        }
+        for func in &mut self.functions {
+            func.for_each_type(f);
+        }
+        // the types aren't part of the keys, so nothing moves
+        self.classes = mem::take(&mut self.classes)
+            .into_iter()
+            .map(|mut class| {
+                class.bases.iter_mut().for_each(|b| b.visit(f));
+                for typ in class.fields.iter_mut().filter_map(|v| v.typ.as_mut()) {
+                    typ.visit(f);
+                }
+                class.constructor.for_each_type(f);
+                class.methods = mem::take(&mut class.methods)
+                    .into_iter()
+                    .map(|mut m| {
+                        m.for_each_type(f);
+                        m
+                    })
+                    .collect();
+                class
+            })
+            .collect();
+    }
 }
 
 impl Function {
-    // pub fn build(name: &str) -> Self {
-    //     Self {
-    //         name: name.to_string(),
-    //         parameters: Vec::new(),
-    //         return_type: Type::Any,
-    //         kind: FunctionKind::Static,
-    //         return_doc: None,
-    //         doc: None,
-    //     }
-    // }
-    // pub fn kind(mut self, kind: FunctionKind) -> Self {
-    //     self.kind = kind;
-    //     self
-    // }
+    pub fn build(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            parameters: Vec::new(),
+            return_type: Type::Any,
+            kind: FunctionKind::Static,
+            return_doc: None,
+            doc: None,
+            deprecated: None,
+            since: None,
+            c_name: None,
+            source: None,
+            finish: None,
+            invoker: None,
+        }
+    }
+    pub fn kind(mut self, kind: FunctionKind) -> Self {
+        self.kind = kind;
+        self
+    }
+    pub fn returns(mut self, typ: Type) -> Self {
+        self.return_type = typ;
+        self
+    }
     pub fn clear_parameters(mut self) -> Self {
         self.parameters.clear();
         self
@@ -127,7 +361,8 @@ impl Function {
             name: name.into(),
             typ,
             optional,
-            doc: doc.map(|s| String::from(s)),
+            doc: doc.map(String::from),
+            since: None,
         })
     }
     pub fn add_variadic_param<'a>(
@@ -140,7 +375,20 @@ impl Function {
         self.add_param(Param::Variadic {
             name: name.into(),
             typ,
-            doc: doc.map(|s| String::from(s)),
+            doc: doc.map(String::from),
+        })
+    }
+    pub fn add_keywords_param<'a>(
+        self,
+        name: &str,
+        typ: Type,
+        doc: impl Into<Option<&'a str>>,
+    ) -> Self {
+        let doc: Option<&str> = doc.into();
+        self.add_param(Param::Keywords {
+            name: name.into(),
+            typ,
+            doc: doc.map(String::from),
         })
     }
     pub fn add_self_param(self) -> Self {
@@ -156,7 +404,6 @@ macro_rules! index_by {
         impl Hash for $struct {
             fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
                 self.$field.hash(state);
-                state.finish();
             }
         }
 
@@ -177,15 +424,113 @@ macro_rules! index_by {
 }
 
 index_by!(Class::name: &str);
-index_by!(Function::name: &str);
 index_by!(Namespace::name: &str);
 
+impl Hash for Function {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Function {}
+
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.0.len().max(other.0.len());
+        let component = |v: &Self, i: usize| v.0.get(i).copied().unwrap_or(0);
+
+        (0..len)
+            .map(|i| component(self, i).cmp(&component(other, i)))
+            .find(|o| o.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Version {}
+
+impl FromStr for Version {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.split('.')
+            .map(|c| c.trim().parse())
+            .collect::<Result<_, _>>()
+            .map(Self)
+            .map_err(|_| format!("invalid version `{}`", s))
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, c) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, ".")?;
+            }
+            write!(f, "{}", c)?;
+        }
+        Ok(())
+    }
+}
+
+/// Formats the deprecation as a short message like `since 4.10: Use X`
+impl std::fmt::Display for Deprecation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.version, &self.doc) {
+            (Some(v), Some(d)) => write!(f, "since {}: {}", v, d),
+            (Some(v), None) => write!(f, "since {}", v),
+            (None, Some(d)) => write!(f, "{}", d),
+            (None, None) => write!(f, "deprecated"),
+        }
+    }
+}
+
+impl std::fmt::Display for SourcePosition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.line {
+            Some(line) => write!(f, "{}:{}", self.filename, line),
+            None => write!(f, "{}", self.filename),
+        }
+    }
+}
+
 impl std::fmt::Display for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::Primitive(s) => write!(f, "{}", s),
             Self::LocalClass(c) => write!(f, "{}", c),
             Self::ExternalClass { module, name } => write!(f, "{}.{}", module, name),
+            Self::Instance => write!(f, "typing.Self"),
+            Self::Var(name) => write!(f, "{}", name),
+            Self::ClassOf(typ) => write!(f, "type[{}]", typ),
+            Self::Optional(typ) => write!(f, "typing.Optional[{}]", typ),
+            Self::Generic(typ, args) => {
+                write!(f, "{}[", typ)?;
+                for (i, arg) in args.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", arg)?;
+                }
+                write!(f, "]")
+            }
             Self::Any => write!(f, "typing.Any"),
         }
     }
@@ -195,8 +540,11 @@ impl std::fmt::Display for Value {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::None => write!(f, "None"),
-            Self::Number(n) => write!(f, "{}", n),
-            Self::Str(s) => write!(f, "\"{}\"", s),
+            Self::Int(n) | Self::Float(n) => write!(f, "{}", n),
+            Self::Bool(true) => write!(f, "True"),
+            Self::Bool(false) => write!(f, "False"),
+            Self::Str(s) => write!(f, "{}", quote(s)),
+            Self::Ellipsis => write!(f, "..."),
         }
     }
 }
@@ -204,12 +552,14 @@ impl std::fmt::Display for Value {
 impl std::fmt::Display for Var {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}", self.name)?;
-        if let Some(typ) = &self.typ {
-            if self.constant {
-                write!(f, ": typing.Final[{}]", typ)?;
-            } else {
-                write!(f, ": {}", typ)?;
+        match (&self.typ, &self.value) {
+            // type checkers infer the literal type of the value
+            (_, Some(value)) if self.constant && !matches!(value, Value::Ellipsis) => {
+                write!(f, ": typing.Final")?
             }
+            (Some(typ), _) if self.constant => write!(f, ": typing.Final[{}]", typ)?,
+            (Some(typ), _) => write!(f, ": {}", typ)?,
+            (None, _) => {}
         }
         if let Some(value) = &self.value {
             write!(f, " = {}", value)?;
@@ -0,0 +1,512 @@
+use std::{borrow::Cow, collections::HashMap};
+
+use clap::ArgEnum;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use crate::declarations::{FunctionKind, Namespace};
+
+/// The markup used by the documentation of a repository, as declared in its
+/// `<doc:format />` tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Markup {
+    /// Markdown with gtk-doc sigils, like `#GtkWidget` or `%TRUE`. Files
+    /// without a `<doc:format />` use it too
+    #[default]
+    GtkDocMarkdown,
+    /// DocBook XML with gtk-doc sigils
+    GtkDocDocbook,
+    /// Markdown with gi-docgen links, like `[method@Gtk.Widget.show]`
+    GiDocgen,
+}
+
+/// The markup written to the docstrings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum Format {
+    /// reStructuredText with Sphinx roles and field lists
+    Sphinx,
+    /// Markdown, with parameters as a list
+    Markdown,
+    /// reStructuredText with Google style sections, like `Args:`
+    Google,
+    /// Text without any markup, for messages and comments
+    #[clap(skip)]
+    Plain,
+}
+
+/// The kind of declaration a reference points to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Class,
+    Method,
+    Function,
+    Constant,
+    Attribute,
+}
+
+/// A declaration that can be referenced from the documentation
+#[derive(Debug)]
+struct Symbol {
+    role: Role,
+    /// python name, like `Gtk.Widget.show`
+    path: String,
+}
+
+/// Maps the C symbols of the analyzed namespaces, like `gtk_widget_show`,
+/// to their python names
+#[derive(Debug, Default)]
+pub struct SymbolIndex {
+    symbols: HashMap<String, Symbol>,
+}
+
+impl SymbolIndex {
+    pub fn new<'a>(namespaces: impl IntoIterator<Item = &'a Namespace>) -> Self {
+        let mut index = Self::default();
+
+        for ns in namespaces {
+            // declarations without a C name are named after the prefixes
+            let type_name = |c_name: &Option<String>, name: &str| {
+                c_name.clone().or_else(|| {
+                    let prefix = ns.c_identifier_prefixes.first()?;
+                    Some(format!("{}{}", prefix, name))
+                })
+            };
+            let function_name = |c_name: &Option<String>, name: &str| {
+                c_name.clone().or_else(|| {
+                    let prefix = ns.c_symbol_prefixes.first()?;
+                    Some(format!("{}_{}", prefix, name))
+                })
+            };
+
+            for c in &ns.constants {
+                let path = format!("{}.{}", ns.name, c.name);
+                index.insert(c.c_name.clone(), Role::Constant, path);
+            }
+            for f in &ns.functions {
+                let path = format!("{}.{}", ns.name, f.name);
+                index.insert(function_name(&f.c_name, &f.name), Role::Function, path);
+            }
+            for e in &ns.enums {
+                let path = format!("{}.{}", ns.name, e.name);
+                for v in &e.values {
+                    let path = format!("{}.{}", path, v.name);
+                    index.insert(v.c_name.clone(), Role::Constant, path);
+                }
+                index.insert(type_name(&e.c_name, &e.name), Role::Class, path);
+            }
+            for c in &ns.classes {
+                let path = format!("{}.{}", ns.name, c.name);
+                for m in &c.methods {
+                    let role = match m.kind {
+                        FunctionKind::Static => Role::Function,
+                        _ => Role::Method,
+                    };
+                    index.insert(m.c_name.clone(), role, format!("{}.{}", path, m.name));
+                }
+                index.insert(type_name(&c.c_name, &c.name), Role::Class, path);
+            }
+        }
+
+        index
+    }
+
+    fn insert(&mut self, c_name: Option<String>, role: Role, path: String) {
+        if let Some(c_name) = c_name {
+            self.symbols.insert(c_name, Symbol { role, path });
+        }
+    }
+
+    fn get(&self, c_name: &str) -> Option<&Symbol> {
+        self.symbols.get(c_name)
+    }
+}
+
+impl Markup {
+    pub fn from_name(name: &str) -> Self {
+        match name {
+            "gi-docgen" => Self::GiDocgen,
+            "gtk-doc-docbook" => Self::GtkDocDocbook,
+            _ => Self::GtkDocMarkdown,
+        }
+    }
+}
+
+impl Format {
+    fn role(self, role: Role, target: &str) -> String {
+        match (self, role) {
+            (Self::Sphinx | Self::Google, Role::Class) => format!(":class:`{}`", target),
+            (Self::Sphinx | Self::Google, Role::Method) => format!(":meth:`{}`", target),
+            (Self::Sphinx | Self::Google, Role::Function) => format!(":func:`{}`", target),
+            (Self::Sphinx | Self::Google, Role::Constant) => format!(":const:`{}`", target),
+            (Self::Sphinx | Self::Google, Role::Attribute) => format!(":attr:`{}`", target),
+            (Self::Markdown, Role::Method | Role::Function) => format!("`{}()`", target),
+            (Self::Markdown, _) => format!("`{}`", target),
+            (Self::Plain, Role::Method | Role::Function) => format!("{}()", target),
+            (Self::Plain, _) => target.to_string(),
+        }
+    }
+
+    /// A signal or property of a class
+    fn member(self, class: &str, member: &str, kind: &str) -> String {
+        format!("{} \"{}\" {}", self.role(Role::Class, class), member, kind)
+    }
+
+    fn literal(self, text: &str) -> String {
+        match self {
+            Self::Sphinx | Self::Google => format!("``{}``", text),
+            Self::Markdown if text.contains('`') => format!("`` {} ``", text),
+            Self::Markdown => format!("`{}`", text),
+            Self::Plain => text.to_string(),
+        }
+    }
+
+    fn param(self, name: &str) -> String {
+        match self {
+            Self::Plain => name.to_string(),
+            _ => format!("*{}*", name),
+        }
+    }
+
+    fn link(self, text: &str, url: &str) -> String {
+        match self {
+            Self::Sphinx | Self::Google => format!("`{} <{}>`_", text, url),
+            Self::Markdown => format!("[{}]({})", text, url),
+            Self::Plain => format!("{} ({})", text, url),
+        }
+    }
+
+    fn heading(self, text: &str) -> String {
+        match self {
+            Self::Sphinx | Self::Google => format!(".. rubric:: {}", text),
+            Self::Markdown => format!("**{}**", text),
+            Self::Plain => text.to_string(),
+        }
+    }
+
+    fn code_block(self, language: &str, code: &[&str], out: &mut Vec<String>) {
+        if out.last().is_some_and(|l| !l.is_empty()) {
+            out.push(String::new());
+        }
+        if self == Self::Markdown {
+            let language = if language == "none" { "" } else { language };
+            out.push(format!("```{}", language));
+            out.extend(code.iter().map(|l| l.to_string()));
+            out.push(String::from("```"));
+            out.push(String::new());
+            return;
+        }
+        if matches!(self, Self::Sphinx | Self::Google) {
+            out.push(format!(".. code-block:: {}", language));
+            out.push(String::new());
+        }
+        for line in code {
+            if line.trim().is_empty() {
+                out.push(String::new());
+            } else {
+                out.push(format!("    {}", line));
+            }
+        }
+        out.push(String::new());
+    }
+}
+
+/// Formats a reference to a method, given its python name
+pub fn method_reference(path: &str, format: Format) -> String {
+    format.role(Role::Method, path)
+}
+
+/// Converts the DocBook tags that have a Markdown equivalent, and removes the
+/// rest
+fn docbook_to_markdown(doc: &str) -> String {
+    lazy_static! {
+        static ref PROGRAMLISTING: Regex =
+            Regex::new(r"(?s)<programlisting[^>]*>(.*?)</programlisting>").unwrap();
+        static ref LITERAL: Regex =
+            Regex::new(r"(?s)<(literal|code|function|type|constant)>(.*?)</\w+>").unwrap();
+        static ref EMPHASIS: Regex = Regex::new(r"(?s)<emphasis>(.*?)</emphasis>").unwrap();
+        static ref LISTITEM: Regex = Regex::new(r"<listitem>\s*").unwrap();
+        static ref PARA: Regex = Regex::new(r"\s*</?(para|simpara)>\s*").unwrap();
+        static ref TAG: Regex = Regex::new(r"</?[a-zA-Z][^>]*>").unwrap();
+    }
+    let doc = PROGRAMLISTING.replace_all(doc, "\n|[$1]|\n");
+    let doc = LITERAL.replace_all(&doc, "`$2`");
+    let doc = EMPHASIS.replace_all(&doc, "*$1*");
+    let doc = LISTITEM.replace_all(&doc, "\n- ");
+    let doc = PARA.replace_all(&doc, "\n\n");
+    let doc = TAG.replace_all(&doc, "");
+
+    doc.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+/// Converts the text of a single line, outside of inline code
+fn convert_text(text: &str, format: Format, symbols: &SymbolIndex) -> String {
+    lazy_static! {
+        static ref GI_DOCGEN_LINK: Regex =
+            Regex::new(r"\[(?P<kind>[a-z]+)@(?P<target>[^\]\s]+)\]").unwrap();
+        static ref LINK: Regex =
+            Regex::new(r"\[(?P<text>[^\]]+)\]\((?P<url>[^)\s]+)\)").unwrap();
+        /// C symbols, with an optional gtk-doc sigil, like `%GTK_ALIGN_FILL`,
+        /// `gtk_widget_show()` or `#GtkButton::clicked`
+        static ref SYMBOL: Regex = Regex::new(
+            r"(?P<pre>^|\W)(?P<sigil>[#%]?)(?P<name>[A-Za-z_][A-Za-z0-9_]*)(?P<call>\(\))?(?:(?P<sep>::?)(?P<member>[a-z0-9][a-z0-9_-]*))?"
+        )
+        .unwrap();
+        static ref PARAM: Regex = Regex::new(r"(?P<pre>^|[^\w])@(?P<name>[a-z_][a-z0-9_]*)").unwrap();
+    }
+
+    let text = GI_DOCGEN_LINK.replace_all(text, |c: &Captures| {
+        let target = &c["target"];
+        match &c["kind"] {
+            "method" | "ctor" => format.role(Role::Method, target),
+            "vfunc" => match target.rsplit_once('.') {
+                Some((class, vfunc)) => {
+                    format.role(Role::Method, &format!("{}.do_{}", class, vfunc))
+                }
+                None => format.role(Role::Method, target),
+            },
+            "func" | "callback" => format.role(Role::Function, target),
+            "const" => format.role(Role::Constant, target),
+            "property" => match target.split_once(':') {
+                Some((class, property)) => format.member(class, property, "property"),
+                None => format.role(Role::Attribute, target),
+            },
+            "signal" => match target.split_once("::") {
+                Some((class, signal)) => format.member(class, signal, "signal"),
+                None => format.role(Role::Attribute, target),
+            },
+            "id" => match symbols.get(target) {
+                Some(symbol) => format.role(symbol.role, &symbol.path),
+                None => format.literal(target),
+            },
+            _ => format.role(Role::Class, target),
+        }
+    });
+    let text = LINK.replace_all(&text, |c: &Captures| format.link(&c["text"], &c["url"]));
+    let text = SYMBOL.replace_all(&text, |c: &Captures| {
+        let (pre, sigil, name) = (&c["pre"], &c["sigil"], &c["name"]);
+        let member = c.name("member").map(|m| (&c["sep"], m.as_str()));
+
+        let symbol = symbols.get(name);
+        if sigil == "#" && pre == "/" {
+            // anchors in urls
+            return c[0].to_string();
+        }
+
+        // signals and properties, of unknown classes too
+        let class = match symbol {
+            Some(Symbol {
+                role: Role::Class,
+                path,
+            }) => Some(path.as_str()),
+            None if sigil == "#" => Some(name),
+            _ => None,
+        };
+        if let (Some(class), Some((sep, member))) = (class, member) {
+            let kind = if sep == "::" { "signal" } else { "property" };
+            return format!("{}{}", pre, format.member(class, member, kind));
+        }
+
+        let reference = match (sigil, symbol) {
+            ("%", _) if name == "TRUE" => format.literal("True"),
+            ("%", _) if name == "FALSE" => format.literal("False"),
+            ("%", _) if name == "NULL" => format.literal("None"),
+            (_, Some(symbol)) => format.role(symbol.role, &symbol.path),
+            // unknown symbols keep their C name
+            ("%", None) => format.role(Role::Constant, name),
+            ("#", None) => format.role(Role::Class, name),
+            _ => return c[0].to_string(),
+        };
+        let member = member
+            .map(|(sep, m)| format!("{}{}", sep, m))
+            .unwrap_or_default();
+        format!("{}{}{}", pre, reference, member)
+    });
+    let text = PARAM.replace_all(&text, |c: &Captures| {
+        format!("{}{}", &c["pre"], format.param(&c["name"]))
+    });
+
+    text.into_owned()
+}
+
+/// Converts a line. Inline code is left untouched, unless it's a C symbol
+fn convert_line(line: &str, format: Format, symbols: &SymbolIndex) -> String {
+    lazy_static! {
+        static ref HEADING: Regex = Regex::new(r"^#{1,6}\s+(.*?)\s*#*$").unwrap();
+        /// single or double backticks, the latter may have backticks inside,
+        /// like ``` `` `x` `` ```
+        static ref INLINE_CODE: Regex = Regex::new(r"``\s?(.+?)\s?``|`([^`]+)`").unwrap();
+    }
+    if let Some(c) = HEADING.captures(line) {
+        return format.heading(&convert_line(&c[1], format, symbols));
+    }
+
+    let mut converted = String::with_capacity(line.len());
+    let mut last = 0;
+    for code in INLINE_CODE.captures_iter(line) {
+        let span = code.get(0).unwrap();
+        converted.push_str(&convert_text(&line[last..span.start()], format, symbols));
+        let code = code.get(1).or_else(|| code.get(2)).unwrap().as_str();
+        match symbols.get(code.trim_end_matches("()")) {
+            Some(symbol) => converted.push_str(&format.role(symbol.role, &symbol.path)),
+            None => converted.push_str(&format.literal(code)),
+        }
+        last = span.end();
+    }
+    converted.push_str(&convert_text(&line[last..], format, symbols));
+    converted
+}
+
+/// Converts the documentation of a repository written in `markup` to the
+/// docstring `format`, replacing the C symbols found in `symbols`
+pub fn convert(doc: &str, markup: Markup, format: Format, symbols: &SymbolIndex) -> String {
+    lazy_static! {
+        static ref GTK_DOC_LANGUAGE: Regex =
+            Regex::new(r#"^\s*<!--\s*language="(\w+)"\s*-->"#).unwrap();
+        static ref FENCE: Regex = Regex::new(r"^\s*(```+|~~~+)\s*(\w*)").unwrap();
+    }
+
+    let doc: Cow<str> = match markup {
+        Markup::GtkDocDocbook => docbook_to_markdown(doc).into(),
+        _ => doc.into(),
+    };
+    let mut out = Vec::new();
+    let mut lines = doc.lines();
+
+    while let Some(line) = lines.next() {
+        if let Some(rest) = line.trim_start().strip_prefix("|[") {
+            // gtk-doc code block, like |[<!-- language="C" --> code ]|
+            let (language, first) = match GTK_DOC_LANGUAGE.captures(rest) {
+                Some(c) => (c[1].to_lowercase(), &rest[c.get(0).unwrap().end()..]),
+                None => (String::from("c"), rest),
+            };
+            let mut code = Vec::new();
+            let mut next = Some(first);
+            while let Some(line) = next {
+                if let Some((last, _)) = line.split_once("]|") {
+                    code.push(last);
+                    break;
+                }
+                code.push(line);
+                next = lines.next();
+            }
+            while code.first().is_some_and(|l| l.trim().is_empty()) {
+                code.remove(0);
+            }
+            while code.last().is_some_and(|l| l.trim().is_empty()) {
+                code.pop();
+            }
+            format.code_block(&language, &code, &mut out);
+        } else if let Some(c) = FENCE.captures(line) {
+            // markdown fenced code block
+            let fence = &c[1];
+            let language = match &c[2] {
+                "" => "none",
+                language => language,
+            };
+            let code: Vec<_> = lines
+                .by_ref()
+                .take_while(|l| !l.trim_start().starts_with(fence))
+                .collect();
+            format.code_block(&language.to_lowercase(), &code, &mut out);
+        } else if !(line.trim().is_empty() && out.last().is_some_and(|l| l.is_empty())) {
+            out.push(convert_line(line, format, symbols));
+        }
+    }
+
+    while out.last().is_some_and(|l| l.is_empty()) {
+        out.pop();
+    }
+    out.join("\n")
+}
+
+/// Escapes the text so it can be placed between triple quotes
+pub fn escape_docstring(doc: &str) -> String {
+    let mut escaped = doc.replace('\\', "\\\\").replace("\"\"\"", "\\\"\\\"\\\"");
+    if escaped.ends_with('"') {
+        escaped.pop();
+        escaped.push_str("\\\"");
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sphinx(doc: &str) -> String {
+        convert(
+            doc,
+            Markup::GtkDocMarkdown,
+            Format::Sphinx,
+            &SymbolIndex::default(),
+        )
+    }
+
+    #[test]
+    fn inline_code() {
+        assert_eq!(sphinx("Call `run()` first"), "Call ``run()`` first");
+        assert_eq!(sphinx("Use ``a `b` c`` here"), "Use ``a `b` c`` here");
+        assert_eq!(
+            convert(
+                "Use ``a `b` c`` here",
+                Markup::GtkDocMarkdown,
+                Format::Markdown,
+                &SymbolIndex::default()
+            ),
+            "Use `` a `b` c `` here"
+        );
+    }
+
+    #[test]
+    fn sigils() {
+        assert_eq!(
+            sphinx("Returns %TRUE if @widget is a #GtkButton"),
+            "Returns ``True`` if *widget* is a :class:`GtkButton`"
+        );
+        assert_eq!(
+            sphinx("Emitted by #GtkButton::clicked"),
+            "Emitted by :class:`GtkButton` \"clicked\" signal"
+        );
+    }
+
+    #[test]
+    fn gi_docgen_links() {
+        let convert = |doc| {
+            convert(
+                doc,
+                Markup::GiDocgen,
+                Format::Sphinx,
+                &SymbolIndex::default(),
+            )
+        };
+        assert_eq!(
+            convert("See [method@Gtk.Widget.show] and [vfunc@Gtk.Widget.snapshot]"),
+            "See :meth:`Gtk.Widget.show` and :meth:`Gtk.Widget.do_snapshot`"
+        );
+        assert_eq!(
+            convert("Bound to [property@Gtk.Widget:visible]"),
+            "Bound to :class:`Gtk.Widget` \"visible\" property"
+        );
+    }
+
+    #[test]
+    fn code_blocks() {
+        assert_eq!(
+            sphinx("Example:\n|[<!-- language=\"Python\" -->\nwidget.show()\n]|\nDone"),
+            "Example:\n\n.. code-block:: python\n\n    widget.show()\n\nDone"
+        );
+        assert_eq!(
+            convert(
+                "```c\nint x;\n```",
+                Markup::GiDocgen,
+                Format::Markdown,
+                &SymbolIndex::default()
+            ),
+            "```c\nint x;\n```"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    declarations::{Namespace, Type},
+    passes::Pass,
+};
+
+/// Records converted by PyGObject to the classes of pycairo
+const CAIRO_TYPES: [&str; 6] = [
+    "Context",
+    "Surface",
+    "Region",
+    "FontOptions",
+    "Pattern",
+    "ScaledFont",
+];
+
+/// Maps foreign records, like `cairo.Context`, to the python classes PyGObject
+/// converts them to. The namespaces of those records don't match what's
+/// available at runtime, so they're left out of the stubs
+#[derive(Debug, Clone)]
+pub struct ForeignTypes {
+    /// (namespace, record) to (module, class)
+    types: HashMap<(String, String), (String, String)>,
+    namespaces: HashSet<String>,
+}
+
+impl Default for ForeignTypes {
+    fn default() -> Self {
+        let mut foreign = Self {
+            types: HashMap::new(),
+            namespaces: HashSet::new(),
+        };
+        for name in CAIRO_TYPES {
+            let cairo = format!("cairo.{}", name);
+            foreign.insert(&cairo, &cairo);
+        }
+        foreign
+    }
+}
+
+impl ForeignTypes {
+    /// Maps the `record`, like `cairo.Context`, to the python `class`, like
+    /// `cairo.Context`. Both names must have a dot
+    pub fn insert(&mut self, record: &str, class: &str) {
+        if let (Some((namespace, record)), Some((module, class))) =
+            (record.split_once('.'), class.rsplit_once('.'))
+        {
+            self.namespaces.insert(namespace.to_string());
+            self.types.insert(
+                (namespace.to_string(), record.to_string()),
+                (module.to_string(), class.to_string()),
+            );
+        }
+    }
+
+    /// Returns `true` if the stub of the namespace should be left out
+    pub fn is_foreign(&self, namespace: &str) -> bool {
+        self.namespaces.contains(namespace)
+    }
+
+    /// Replaces the foreign records used by the namespace with their python
+    /// classes. Other records of foreign namespaces can't be used from python
+    pub fn apply(&self, ns: &mut Namespace) {
+        let mut modules = HashSet::new();
+
+        ns.for_each_type(&mut |typ| {
+            if let Type::ExternalClass { module, name } = typ {
+                match self.types.get(&(module.clone(), name.clone())) {
+                    Some((module, class)) => {
+                        modules.insert(module.clone());
+                        *typ = Type::ExternalClass {
+                            module: module.clone(),
+                            name: class.clone(),
+                        };
+                    }
+                    None if self.is_foreign(module) => *typ = Type::Any,
+                    None => {}
+                }
+            }
+        });
+
+        ns.imports.retain(|i| !self.is_foreign(i));
+        ns.modules.extend(modules);
+    }
+}
+
+impl Pass for ForeignTypes {
+    fn namespace(&mut self, ns: &mut Namespace) -> Result<(), String> {
+        self.apply(ns);
+        Ok(())
+    }
+}
@@ -1,12 +1,99 @@
-use std::io::{self, Cursor, Write};
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
 
+use clap::ArgEnum;
 use lazy_static::lazy_static;
 use regex::Regex;
 
-use crate::declarations::{Class, Enumeration, Function, FunctionKind, Namespace, Param, Var};
+use crate::{
+    declarations::{
+        Class, Deprecation, Enumeration, Function, FunctionKind, Namespace, Param, Snippet,
+        SourcePosition, Type, Var, Version,
+    },
+    docs::{self, Format, Markup, SymbolIndex},
+    passes::{walk_class, walk_enumeration, walk_namespace, Pass},
+};
 
-pub struct PythonGenerator<W: Write> {
+/// How much of the documentation gets written to the docstrings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ArgEnum)]
+pub enum DocStyle {
+    /// No docstrings at all
+    None,
+    /// Only the first sentence of the descriptions of functions and their
+    /// parameters. Classes and enums keep the whole ones
+    Summary,
+    /// The whole descriptions
+    Full,
+}
+
+/// Settings that change what gets written by the [`PythonGenerator`]
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Python version the stubs are targeting
+    pub python_version: Version,
+    /// Oldest version of each namespace the stubs should work with. Anything
+    /// introduced after it is left out
+    pub target_versions: HashMap<String, Version>,
+    /// Mark declarations newer than the target version as deprecated instead
+    /// of leaving them out
+    pub warn_newer: bool,
+    /// How much of the documentation goes in the docstrings
+    pub doc_style: DocStyle,
+    /// Markup of the docstrings
+    pub doc_format: Format,
+    /// Add comments with the C symbol and header position of each
+    /// declaration
+    pub provenance: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            python_version: Version(vec![3, 8]),
+            target_versions: HashMap::new(),
+            warn_newer: false,
+            doc_style: DocStyle::Summary,
+            doc_format: Format::Sphinx,
+            provenance: false,
+        }
+    }
+}
+
+impl Options {
+    /// `typing_extensions.deprecated` (PEP 702) is only available for Python
+    /// 3.7 and newer, older targets get a note in the docstring instead
+    fn deprecation_decorator(&self) -> bool {
+        self.python_version >= Version(vec![3, 7])
+    }
+
+    /// `typing.final` was added in Python 3.8
+    fn final_decorator(&self) -> &'static str {
+        if self.python_version >= Version(vec![3, 8]) {
+            "typing.final"
+        } else {
+            "typing_extensions.final"
+        }
+    }
+
+    /// `typing.Self` was added in Python 3.11
+    fn self_type(&self) -> &'static str {
+        if self.python_version >= Version(vec![3, 11]) {
+            "typing.Self"
+        } else {
+            "typing_extensions.Self"
+        }
+    }
+}
+
+pub struct PythonGenerator<'a, W: Write> {
     writer: W,
+    options: &'a Options,
+    /// C symbols that can be referenced from the documentation
+    symbols: &'a SymbolIndex,
+    /// markup of the documentation of the namespace being written
+    markup: Markup,
 }
 
 #[derive(Clone, Copy)]
@@ -35,6 +122,173 @@ impl Indent {
     }
 }
 
+/// Quotes a string as a python string literal
+pub fn quote(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\x{:02x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The version of a namespace targeted with [`Options::target_versions`]
+struct TargetVersion<'a> {
+    namespace: &'a str,
+    version: &'a Version,
+    warn: bool,
+}
+
+impl TargetVersion<'_> {
+    /// Returns `false` if a declaration introduced in `since` has to be left
+    /// out, otherwise it's kept, but it may be marked as deprecated
+    fn check(&self, since: &Option<Version>, deprecated: &mut Option<Deprecation>) -> bool {
+        let since = match since {
+            Some(since) if since > self.version => since,
+            _ => return true,
+        };
+        if self.warn {
+            *deprecated = Some(Deprecation {
+                version: None,
+                doc: Some(format!(
+                    "Only available since {} {}, newer than the targeted {}",
+                    self.namespace, since, self.version
+                )),
+            });
+        }
+        self.warn
+    }
+}
+
+/// Leaves out, or marks as deprecated, what's newer than the versions
+/// targeted by [`Options::target_versions`]
+pub struct VersionFilter<'a> {
+    options: &'a Options,
+    /// name of the namespace being filtered
+    namespace: String,
+}
+
+impl<'a> VersionFilter<'a> {
+    pub fn new(options: &'a Options) -> Self {
+        Self {
+            options,
+            namespace: String::new(),
+        }
+    }
+
+    fn target<'n>(&self, namespace: &'n str) -> Option<TargetVersion<'n>>
+    where
+        'a: 'n,
+    {
+        self.options
+            .target_versions
+            .get(namespace)
+            .map(|version| TargetVersion {
+                namespace,
+                version,
+                warn: self.options.warn_newer,
+            })
+    }
+
+    /// Checks a declaration of the namespace being filtered
+    fn check(&self, since: &Option<Version>, deprecated: &mut Option<Deprecation>) -> bool {
+        self.target(&self.namespace)
+            .is_none_or(|t| t.check(since, deprecated))
+    }
+
+    /// Checks the keyword arguments taken from properties, which may come
+    /// from the classes of other namespaces. They can't be marked as
+    /// deprecated, so their docs start with a note instead
+    fn check_params(&self, func: &mut Function) {
+        func.parameters.retain_mut(|p| {
+            let (doc, namespace, since) = match p {
+                Param::Named {
+                    doc,
+                    since: Some((namespace, since)),
+                    ..
+                } => (doc, namespace, since),
+                _ => return true,
+            };
+            let target = match self.target(namespace) {
+                Some(target) => target,
+                None => return true,
+            };
+            let mut deprecated = None;
+            let keep = target.check(&Some(since.clone()), &mut deprecated);
+            if let Some(d) = deprecated {
+                *doc = Some(match doc.take() {
+                    Some(doc) => format!("{}\n\n{}", d, doc),
+                    None => d.to_string(),
+                });
+            }
+            keep
+        });
+    }
+}
+
+impl Pass for VersionFilter<'_> {
+    fn namespace(&mut self, ns: &mut Namespace) -> Result<(), String> {
+        // the namespaces without a target are visited too, their
+        // constructors may take properties of the targeted ones
+        self.namespace = ns.name.clone();
+        walk_namespace(self, ns);
+        Ok(())
+    }
+
+    fn class(&mut self, class: &mut Class) -> bool {
+        walk_class(self, class);
+        self.check(&class.since, &mut class.deprecated)
+    }
+
+    fn enumeration(&mut self, enumeration: &mut Enumeration) -> bool {
+        walk_enumeration(self, enumeration);
+        self.check(&enumeration.since, &mut enumeration.deprecated)
+    }
+
+    fn var(&mut self, var: &mut Var) -> bool {
+        self.check(&var.since, &mut var.deprecated)
+    }
+
+    fn function(&mut self, func: &mut Function) -> bool {
+        self.check_params(func);
+        self.check(&func.since, &mut func.deprecated)
+    }
+}
+
+/// The parts of a docstring, put together by [`PythonGenerator::docstring`]
+#[derive(Default)]
+struct Docstring {
+    doc: Option<String>,
+    /// the doc is written in python already, and goes as is
+    raw: bool,
+    /// the doc isn't shortened to a summary, like the ones of classes
+    whole: bool,
+    /// names and descriptions of the parameters
+    params: Vec<(String, String)>,
+    returns: Option<String>,
+    /// python name of the method calling this virtual method
+    invoker: Option<String>,
+    /// only subclasses can be instantiated
+    is_abstract: bool,
+    since: Option<Version>,
+    /// only set when it can't be marked with a decorator
+    deprecated: Option<Deprecation>,
+}
+
+/// Indents every line but the first one by `width` spaces
+fn hang(text: &str, width: usize) -> String {
+    text.replace('\n', &format!("\n{}", " ".repeat(width)))
+}
+
 fn summarize(mut doc: String) -> String {
     lazy_static! {
         static ref PAT: Regex = Regex::new(r"[.]\s").unwrap();
@@ -74,16 +328,50 @@ write!(
 
 */
 
-impl<W: Write> PythonGenerator<W> {
-    pub fn new(writer: W) -> Self {
-        Self { writer }
+impl<'a, W: Write> PythonGenerator<'a, W> {
+    pub fn new(writer: W, options: &'a Options, symbols: &'a SymbolIndex) -> Self {
+        Self {
+            writer,
+            options,
+            symbols,
+            markup: Markup::default(),
+        }
     }
 
-    pub fn write_namespace(&mut self, ns: Namespace) -> io::Result<()> {
+    pub fn write_namespace(&mut self, mut ns: Namespace) -> io::Result<()> {
+        self.markup = ns.doc_markup;
+
+        // `typing.Self` may be too new for the targeted python version, also
+        // inside other types, like `typing.Optional[typing.Self]`
+        let self_type = self.options.self_type();
+        let mut uses_self = false;
+        ns.for_each_type(&mut |typ| {
+            if matches!(typ, Type::Instance) {
+                *typ = Type::Primitive(self_type.into());
+                uses_self = true;
+            }
+        });
+
         writeln!(self.writer, "import typing")?;
-        if ns.enums.len() > 0 {
+        // these may be imported by the extra stubs too
+        ns.modules.remove("typing");
+        if ns.modules.remove("typing_extensions") || self.needs_typing_extensions(&ns, uses_self) {
+            writeln!(self.writer, "import typing_extensions")?;
+        }
+        if ns.modules.remove("abc") {
+            writeln!(self.writer, "import abc")?;
+        }
+        if ns.modules.remove("enum") || !ns.enums.is_empty() {
             writeln!(self.writer, "import enum")?;
         }
+        let mut modules: Vec<_> = ns.modules.iter().collect();
+        modules.sort();
+        for module in modules {
+            writeln!(self.writer, "import {}", module)?;
+        }
+        for import in &ns.extra_imports {
+            writeln!(self.writer, "{}", import)?;
+        }
         for (i, import) in ns.imports.iter().enumerate() {
             if i == 0 {
                 write!(self.writer, "from gi.repository import ")?;
@@ -94,6 +382,15 @@ impl<W: Write> PythonGenerator<W> {
         }
         writeln!(self.writer)?;
 
+        for type_var in ns.type_vars {
+            writeln!(
+                self.writer,
+                "{} = typing.TypeVar({})",
+                type_var,
+                quote(&type_var)
+            )?;
+        }
+
         for c in ns.constants {
             self.write_constant(c)?;
         }
@@ -106,46 +403,306 @@ impl<W: Write> PythonGenerator<W> {
         for c in ns.classes {
             self.write_class(c)?;
         }
+        for snippet in ns.snippets {
+            self.write_snippet(snippet, Indent(0))?;
+        }
 
         Ok(())
     }
 
+    /// Whether the decorators or the `Self` type written for the namespace
+    /// come from `typing_extensions`, being too new for the targeted python
+    fn needs_typing_extensions(&self, ns: &Namespace, uses_self: bool) -> bool {
+        let too_new = |name: &str| name.starts_with("typing_extensions.");
+        let deprecated = |class: &Class| {
+            // abstract classes get a deprecated constructor, unless the extra
+            // stubs replace it
+            let constructor = !class.snippets.iter().any(|s| s.name == "__init__")
+                && (class.is_abstract || class.constructor.deprecated.is_some());
+            class.deprecated.is_some()
+                || constructor
+                || class.methods.iter().any(|m| m.deprecated.is_some())
+        };
+
+        (uses_self && too_new(self.options.self_type()))
+            || (too_new(self.options.final_decorator()) && ns.classes.iter().any(|c| c.is_final))
+            || (self.options.deprecation_decorator()
+                && (ns.enums.iter().any(|e| e.deprecated.is_some())
+                    || ns.functions.iter().any(|f| f.deprecated.is_some())
+                    || ns.classes.iter().any(deprecated)))
+    }
+
+    /// Converts the documentation to the markup used by docstrings, and
+    /// shortens it if only summaries are wanted
+    fn doc(&self, doc: &str) -> String {
+        let doc = self.whole_doc(doc);
+        match self.options.doc_style {
+            DocStyle::Summary => summarize(doc),
+            _ => doc,
+        }
+    }
+
+    /// Converts the documentation to the markup used by docstrings
+    fn whole_doc(&self, doc: &str) -> String {
+        docs::convert(doc, self.markup, self.options.doc_format, self.symbols)
+    }
+
+    /// Converts the documentation to plain text, for messages and comments
+    fn plain_doc(&self, doc: &str) -> String {
+        docs::convert(doc, self.markup, Format::Plain, self.symbols)
+    }
+
+    fn version_note(&self, since: &Version) -> String {
+        match self.options.doc_format {
+            Format::Markdown => format!("*Added in version {}*", since),
+            _ => format!(".. versionadded:: {}", since),
+        }
+    }
+
+    /// Formats the deprecation as a Sphinx `deprecated` directive, or as a
+    /// plain sentence when the version is unknown
+    fn deprecation_note(&self, deprecated: &Deprecation) -> String {
+        let doc = deprecated.doc.as_ref().map(|d| self.doc(d));
+        match (self.options.doc_format, &deprecated.version, doc) {
+            (Format::Markdown, Some(v), Some(d)) => format!("**Deprecated since {}:** {}", v, d),
+            (Format::Markdown, Some(v), None) => format!("**Deprecated since {}**", v),
+            (Format::Markdown, None, Some(d)) => format!("**Deprecated:** {}", d),
+            (Format::Markdown, None, None) => String::from("**Deprecated**"),
+            (_, Some(v), Some(d)) => format!(".. deprecated:: {}\n    {}", v, hang(&d, 4)),
+            (_, Some(v), None) => format!(".. deprecated:: {}", v),
+            (_, None, Some(d)) => format!("Deprecated: {}", d),
+            (_, None, None) => String::from("Deprecated."),
+        }
+    }
+
+    /// Puts together the text of a docstring in the targeted format. Returns
+    /// `None` if it would be empty
+    fn docstring(&self, parts: Docstring) -> Option<String> {
+        if self.options.doc_style == DocStyle::None {
+            return None;
+        }
+        let mut sections: Vec<_> = parts
+            .doc
+            .iter()
+            .map(|d| match (parts.raw, parts.whole) {
+                (true, _) => d.clone(),
+                (_, true) => self.whole_doc(d),
+                _ => self.doc(d),
+            })
+            .collect();
+
+        let params: Vec<_> = parts
+            .params
+            .iter()
+            .map(|(name, d)| (name, self.doc(d)))
+            .collect();
+        let returns = parts.returns.map(|d| self.doc(&d));
+
+        match self.options.doc_format {
+            Format::Google => {
+                if !params.is_empty() {
+                    let args: Vec<_> = params
+                        .iter()
+                        .map(|(name, d)| format!("    {}: {}", name, hang(d, 8)))
+                        .collect();
+                    sections.push(format!("Args:\n{}", args.join("\n")));
+                }
+                sections.extend(returns.map(|d| format!("Returns:\n    {}", hang(&d, 4))));
+            }
+            Format::Markdown => {
+                if !params.is_empty() {
+                    let args: Vec<_> = params
+                        .iter()
+                        .map(|(name, d)| format!("- `{}`: {}", name, hang(d, 2)))
+                        .collect();
+                    sections.push(format!("Parameters:\n{}", args.join("\n")));
+                }
+                sections.extend(returns.map(|d| format!("Returns: {}", d)));
+            }
+            Format::Sphinx | Format::Plain => {
+                let mut fields: Vec<_> = params
+                    .iter()
+                    .map(|(name, d)| format!(":param {}: {}", name, hang(d, 4)))
+                    .collect();
+                fields.extend(returns.map(|d| format!(":return: {}", hang(&d, 4))));
+                if !fields.is_empty() {
+                    sections.push(fields.join("\n"));
+                }
+            }
+        }
+
+        sections.extend(parts.invoker.map(|invoker| {
+            format!(
+                "Override it to change the behavior of {}.",
+                docs::method_reference(&invoker, self.options.doc_format)
+            )
+        }));
+        if parts.is_abstract {
+            sections.push(String::from(
+                "Abstract class, only its subclasses can be instantiated.",
+            ));
+        }
+        sections.extend(parts.since.map(|v| self.version_note(&v)));
+        sections.extend(parts.deprecated.map(|d| self.deprecation_note(&d)));
+
+        (!sections.is_empty()).then(|| sections.join("\n\n"))
+    }
+
     fn write_docstring(&mut self, doc: Option<String>, indent: Indent) -> io::Result<bool> {
         if let Some(doc) = doc {
-            let doc = indent.align(doc);
+            let doc = indent.align(docs::escape_docstring(&doc));
 
-            writeln!(self.writer, "{i}\"\"\"\n{i}{}\n{i}\"\"\"", doc, i = indent)?;
+            if doc.contains('\n') {
+                writeln!(self.writer, "{i}\"\"\"{}\n{i}\"\"\"", doc, i = indent)?;
+            } else {
+                writeln!(self.writer, "{}\"\"\"{}\"\"\"", indent, doc)?;
+            }
             return Ok(true);
         }
         Ok(false)
     }
 
+    /// Writes the PEP 702 decorator if the declaration is deprecated. Returns
+    /// `false` if a note should be added to the docstring instead
+    fn write_deprecated(
+        &mut self,
+        deprecated: &Option<Deprecation>,
+        indent: Indent,
+    ) -> io::Result<bool> {
+        match deprecated {
+            Some(d) if self.options.deprecation_decorator() => {
+                let message = summarize(self.plain_doc(&d.to_string()));
+                writeln!(
+                    self.writer,
+                    "{}@typing_extensions.deprecated({})",
+                    indent,
+                    quote(&message)
+                )?;
+                Ok(true)
+            }
+            Some(_) => Ok(false),
+            None => Ok(true),
+        }
+    }
+
     /// Writes a constant [`Var`] as a python variable definition annotated with
     /// `typing.Final[T]`, and adds an inline comment with the truncated
     /// documentation
     fn write_constant(&mut self, var: Var) -> io::Result<()> {
         write!(self.writer, "{}", var)?;
-        if let Some(mut doc) = var.doc {
-            doc.find('\n').map(|s| doc.truncate(s));
-            write!(self.writer, " # {}", doc)?;
+        let mut comment = None;
+        if self.options.doc_style != DocStyle::None {
+            comment = var.doc.as_ref().map(|doc| {
+                let mut doc = self.plain_doc(doc);
+                if let Some(s) = doc.find('\n') {
+                    doc.truncate(s);
+                }
+                doc
+            });
+            let note = match (&var.since, &var.deprecated) {
+                (_, Some(d)) => Some(format!(
+                    "Deprecated {}",
+                    summarize(self.plain_doc(&d.to_string()))
+                )),
+                (Some(since), None) => Some(format!("Since {}", since)),
+                (None, None) => None,
+            };
+            if let Some(note) = note {
+                comment = Some(match comment {
+                    Some(doc) => format!("{} ({})", doc, note),
+                    None => note,
+                });
+            }
+        }
+        if let Some(provenance) = self.provenance(&var.c_name, &var.source) {
+            comment = Some(match comment {
+                Some(doc) => format!("{} | {}", doc, provenance),
+                None => provenance,
+            });
+        }
+        if let Some(comment) = comment {
+            write!(self.writer, " # {}", comment)?;
         }
         writeln!(self.writer)
     }
 
+    /// Formats the C symbol and the header position of a declaration, when
+    /// they're wanted
+    fn provenance(
+        &self,
+        c_name: &Option<String>,
+        source: &Option<SourcePosition>,
+    ) -> Option<String> {
+        if !self.options.provenance {
+            return None;
+        }
+        match (c_name, source) {
+            (Some(c_name), Some(source)) => Some(format!("{} at {}", c_name, source)),
+            (Some(c_name), None) => Some(c_name.clone()),
+            (None, Some(source)) => Some(source.to_string()),
+            (None, None) => None,
+        }
+    }
+
+    /// Ends the line of a definition, adding the provenance comment if any
+    fn end_definition(
+        &mut self,
+        c_name: &Option<String>,
+        source: &Option<SourcePosition>,
+    ) -> io::Result<()> {
+        match self.provenance(c_name, source) {
+            Some(provenance) => writeln!(self.writer, " # {}", provenance),
+            None => writeln!(self.writer),
+        }
+    }
+
     fn write_enum(&mut self, enumeration: Enumeration) -> io::Result<()> {
-        writeln!(self.writer, "class {}(enum.Enum):", enumeration.name)?;
+        let mut docstring = Docstring {
+            doc: enumeration.doc,
+            whole: true,
+            since: enumeration.since,
+            ..Default::default()
+        };
+        if !self.write_deprecated(&enumeration.deprecated, Indent(0))? {
+            docstring.deprecated = enumeration.deprecated;
+        }
+        write!(self.writer, "class {}(enum.Enum):", enumeration.name)?;
+        self.end_definition(&enumeration.c_name, &enumeration.source)?;
 
-        self.write_docstring(enumeration.doc, Indent(1))?;
+        let doc = self.docstring(docstring);
+        self.write_docstring(doc, Indent(1))?;
 
         for v in enumeration.values {
             writeln!(self.writer, "    {}", v)?;
-            self.write_docstring(v.doc, Indent(1))?;
+            // enum members can't be decorated
+            let doc = self.docstring(Docstring {
+                doc: v.doc,
+                whole: true,
+                since: v.since,
+                deprecated: v.deprecated,
+                ..Default::default()
+            });
+            self.write_docstring(doc, Indent(1))?;
         }
 
         Ok(())
     }
 
     fn write_class(&mut self, class: Class) -> io::Result<()> {
+        let mut docstring = Docstring {
+            doc: class.doc,
+            raw: class.raw_doc,
+            whole: true,
+            is_abstract: class.is_abstract,
+            since: class.since,
+            ..Default::default()
+        };
+        if !self.write_deprecated(&class.deprecated, Indent(0))? {
+            docstring.deprecated = class.deprecated;
+        }
+        if class.is_final {
+            writeln!(self.writer, "@{}", self.options.final_decorator())?;
+        }
         write!(self.writer, "class {}(", class.name)?;
 
         for (i, base) in class.bases.iter().enumerate() {
@@ -155,23 +712,44 @@ impl<W: Write> PythonGenerator<W> {
             write!(self.writer, "{}", base)?;
         }
 
-        writeln!(self.writer, "):")?;
+        write!(self.writer, "):")?;
+        self.end_definition(&class.c_name, &class.source)?;
 
         let body_indent = Indent(1);
         let mut empty = true;
 
-        empty = empty && !self.write_docstring(class.doc, body_indent)?;
+        let doc = self.docstring(docstring);
+        empty = empty && !self.write_docstring(doc, body_indent)?;
 
         for field in class.fields {
             writeln!(self.writer, "{}{}", body_indent, field)?;
             empty = false;
         }
-        self.write_function(class.constructor, body_indent)?;
+        // the extra stubs may replace the constructor
+        if !class.snippets.iter().any(|s| s.name == "__init__") {
+            let mut constructor = class.constructor;
+            // type checkers flag deprecated calls, and subclasses always
+            // define their own constructor, so only this class is flagged
+            if class.is_abstract && constructor.deprecated.is_none() {
+                constructor.deprecated = Some(Deprecation {
+                    version: None,
+                    doc: Some(format!(
+                        "{} is abstract, instantiate a subclass",
+                        class.name
+                    )),
+                });
+            }
+            self.write_function(constructor, body_indent)?;
+        }
 
         for method in class.methods {
             self.write_function(method, body_indent)?;
             empty = false;
         }
+        for snippet in class.snippets {
+            self.write_snippet(snippet, body_indent)?;
+            empty = false;
+        }
 
         if empty {
             writeln!(self.writer, "{}...", body_indent)?;
@@ -180,24 +758,81 @@ impl<W: Write> PythonGenerator<W> {
         Ok(())
     }
 
+    /// Writes the source of the snippet as is, indented
+    fn write_snippet(&mut self, snippet: Snippet, indent: Indent) -> io::Result<()> {
+        for line in snippet.source.lines() {
+            if line.is_empty() {
+                writeln!(self.writer)?;
+            } else {
+                writeln!(self.writer, "{}{}", indent, line)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a function. Asynchronous functions get an overload without the
+    /// callback parameters that returns an awaitable instead, and the callback
+    /// is required in the other one, so a call matches only one of them
     fn write_function(&mut self, mut func: Function, indent: Indent) -> io::Result<()> {
+        match func.finish.take() {
+            Some(finish) => {
+                let mut awaitable = func.clone();
+                awaitable.parameters.retain(|p| match p {
+                    Param::Named { name, .. } | Param::Variadic { name, .. } => {
+                        !finish.callback_params.contains(name)
+                    }
+                    _ => true,
+                });
+                awaitable.return_type =
+                    Type::Primitive(format!("typing.Awaitable[{}]", finish.result));
+
+                self.write_def(awaitable, indent, true, None)?;
+                let callback = finish.callback_params.first().map(String::as_str);
+                self.write_def(func, indent, true, callback)
+            }
+            None => self.write_def(func, indent, false, None),
+        }
+    }
+
+    /// Writes the definition of a function. The parameters up to `callback`
+    /// have no default value, and the callback isn't optional
+    fn write_def(
+        &mut self,
+        mut func: Function,
+        indent: Indent,
+        overload: bool,
+        callback: Option<&str>,
+    ) -> io::Result<()> {
         let body_indent = indent + 1;
+        let mut required = func
+            .parameters
+            .iter()
+            .any(|p| matches!(p, Param::Named { name, .. } if Some(name.as_str()) == callback));
 
-        // using cursor because it's easier to write to
-        let mut docstring = Cursor::new(Vec::new());
+        let mut docstring = Docstring {
+            doc: func.doc.take(),
+            returns: func.return_doc.take(),
+            invoker: func.invoker.take(),
+            since: func.since.take(),
+            ..Default::default()
+        };
 
-        if let Some(doc) = func.doc {
-            docstring.write(summarize(doc).as_bytes())?;
-            docstring.write(b"\n\n")?;
+        if overload {
+            writeln!(self.writer, "{}@typing.overload", indent)?;
         }
 
         match func.kind {
             FunctionKind::StaticMethod => writeln!(self.writer, "{}@staticmethod", indent)?,
+            FunctionKind::ClassMethod => writeln!(self.writer, "{}@classmethod", indent)?,
             // virtual methods have a do_ prefix
             FunctionKind::Virtual => func.name.insert_str(0, "do_"),
             _ => {}
         }
 
+        if !self.write_deprecated(&func.deprecated, indent)? {
+            docstring.deprecated = func.deprecated.take();
+        }
+
         write!(self.writer, "{}def {}(", indent, func.name)?;
 
         for (i, p) in func.parameters.drain(..).enumerate() {
@@ -213,53 +848,230 @@ impl<W: Write> PythonGenerator<W> {
                     doc,
                     optional,
                     typ,
+                    ..
                 } => {
-                    if optional {
-                        write!(self.writer, "{}: typing.Optional[{}] = None", name, typ)?;
+                    let is_callback = Some(name.as_str()) == callback;
+                    let default = if required { "" } else { " = None" };
+                    if optional && !is_callback {
+                        write!(self.writer, "{}: typing.Optional[{}]{}", name, typ, default)?;
                     } else {
-                        write!(self.writer, "{}: {} = None", name, typ)?;
+                        write!(self.writer, "{}: {}{}", name, typ, default)?;
                     }
+                    required &= !is_callback;
                     param_doc = doc.map(|d| (name, d))
                 }
                 Param::Variadic { name, doc, typ } => {
                     write!(self.writer, "*{}: {}", name, typ)?;
                     param_doc = doc.map(|d| (name, d))
                 }
-                Param::Instance => {
-                    write!(self.writer, "self")?;
+                Param::Keywords { name, doc, typ } => {
+                    write!(self.writer, "**{}: {}", name, typ)?;
+                    param_doc = doc.map(|d| (name, d))
                 }
+                Param::Instance => match func.kind {
+                    FunctionKind::ClassMethod => write!(self.writer, "cls")?,
+                    _ => write!(self.writer, "self")?,
+                },
                 Param::Star => {
                     write!(self.writer, "*")?;
                 }
             };
 
-            if let Some((name, doc)) = param_doc {
-                writeln!(
-                    docstring,
-                    "{}:param {}: {}",
-                    body_indent,
-                    name,
-                    summarize(doc)
-                )?;
-            }
+            docstring.params.extend(param_doc);
         }
 
         write!(self.writer, ") -> {}:", func.return_type)?;
+        self.end_definition(&func.c_name, &func.source)?;
 
-        if let Some(doc) = func.return_doc {
-            writeln!(docstring, "{}:return: {}", body_indent, summarize(doc))?;
+        let doc = self.docstring(docstring);
+        if !self.write_docstring(doc, body_indent)? {
+            writeln!(self.writer, "{}...", body_indent)?;
         }
 
-        let docstring = docstring.into_inner();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::analyze_gir;
 
-        if docstring.is_empty() {
-            writeln!(self.writer, "\n{}...", body_indent)?;
-        } else {
-            write!(self.writer, "\n{i}\"\"\"", i = body_indent)?;
-            self.writer.write(&docstring)?;
-            writeln!(self.writer, "{}\"\"\"", body_indent)?;
+    const GIR: &str = r#"<repository><namespace name="Test" version="2.0">
+        <constant name="OLD" value="1"><type name="gint"/></constant>
+        <constant name="NEW" value="2" version="2.2"><type name="gint"/></constant>
+        <enumeration name="Mode">
+            <member name="on" value="1"/>
+            <member name="auto" value="2" version="2.4"/>
+        </enumeration>
+        <function name="setup" version="2.2">
+            <return-value><type name="none"/></return-value>
+        </function>
+        <class name="Widget" version="2.0">
+            <property name="label"><type name="utf8"/></property>
+            <property name="icon" version="2.6"><type name="utf8"/></property>
+            <method name="show">
+                <return-value><type name="none"/></return-value>
+                <parameters>
+                    <instance-parameter name="self"><type name="Widget"/></instance-parameter>
+                </parameters>
+            </method>
+            <method name="animate" version="2.4">
+                <return-value><type name="none"/></return-value>
+                <parameters>
+                    <instance-parameter name="self"><type name="Widget"/></instance-parameter>
+                </parameters>
+            </method>
+        </class>
+        <class name="Popover" version="2.8"/>
+    </namespace></repository>"#;
+
+    fn options(targets: &[(&str, &str)], warn_newer: bool) -> Options {
+        Options {
+            target_versions: targets
+                .iter()
+                .map(|(ns, v)| (ns.to_string(), v.parse().unwrap()))
+                .collect(),
+            warn_newer,
+            ..Default::default()
         }
+    }
 
-        Ok(())
+    fn filtered(options: &Options) -> Namespace {
+        let mut ns = analyze_gir(GIR);
+        // fields only come from the overrides
+        let mut widget = ns.classes.shift_take("Widget").unwrap();
+        widget.fields.push(Var {
+            since: Some(Version(vec![2, 4])),
+            ..ns.constants[0].clone()
+        });
+        ns.classes.insert(widget);
+
+        VersionFilter::new(options).namespace(&mut ns).unwrap();
+        ns
+    }
+
+    fn names<'a>(names: impl Iterator<Item = &'a String>) -> Vec<&'a str> {
+        names.map(String::as_str).collect()
+    }
+
+    fn kwargs(func: &Function) -> Vec<(&str, Option<&str>)> {
+        func.parameters
+            .iter()
+            .filter_map(|p| match p {
+                Param::Named { name, doc, .. } => Some((name.as_str(), doc.as_deref())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn no_target() {
+        let ns = filtered(&options(&[], false));
+        assert_eq!(ns.constants.len(), 2);
+        assert_eq!(ns.classes.len(), 2);
+    }
+
+    #[test]
+    fn leaves_out_newer() {
+        let ns = filtered(&options(&[("Test", "2.2")], false));
+
+        assert_eq!(names(ns.constants.iter().map(|c| &c.name)), ["OLD", "NEW"]);
+        assert_eq!(names(ns.enums[0].values.iter().map(|v| &v.name)), ["ON"]);
+        assert_eq!(names(ns.functions.iter().map(|f| &f.name)), ["setup"]);
+        assert_eq!(names(ns.classes.iter().map(|c| &c.name)), ["Widget"]);
+
+        let widget = ns.classes.get("Widget").unwrap();
+        assert_eq!(names(widget.methods.iter().map(|m| &m.name)), ["show"]);
+        assert!(widget.fields.is_empty());
+        assert_eq!(kwargs(&widget.constructor), [("label", None)]);
+    }
+
+    #[test]
+    fn warns_about_newer() {
+        let ns = filtered(&options(&[("Test", "2.2")], true));
+
+        assert_eq!(ns.enums[0].values.len(), 2);
+        let auto = &ns.enums[0].values[1];
+        let note = "Only available since Test 2.4, newer than the targeted 2.2";
+        assert_eq!(auto.deprecated.as_ref().unwrap().to_string(), note);
+
+        let popover = ns.classes.get("Popover").unwrap();
+        assert!(popover.deprecated.is_some());
+        let widget = ns.classes.get("Widget").unwrap();
+        assert!(widget.deprecated.is_none());
+        assert!(widget.fields[0].deprecated.is_some());
+        let animate = widget.find_method("animate").unwrap();
+        assert!(widget.methods[animate].deprecated.is_some());
+        assert_eq!(
+            kwargs(&widget.constructor),
+            [
+                ("label", None),
+                (
+                    "icon",
+                    Some("Only available since Test 2.6, newer than the targeted 2.2")
+                )
+            ]
+        );
+    }
+
+    #[test]
+    fn per_namespace() {
+        // the targets of other namespaces don't change this one
+        let ns = filtered(&options(&[("Other", "1.0")], false));
+        assert_eq!(ns.classes.len(), 2);
+
+        // but the keyword arguments inherited from them are checked with
+        // their own target
+        let mut ns = analyze_gir(&GIR.replace(r#"name="Test""#, r#"name="Other""#));
+        let mut popover = ns.classes.pop().unwrap();
+        popover.constructor.parameters.push(Param::Named {
+            name: String::from("icon"),
+            typ: Type::Any,
+            doc: None,
+            optional: true,
+            since: Some((String::from("Test"), Version(vec![2, 6]))),
+        });
+        ns.classes.insert(popover);
+
+        let options = options(&[("Test", "2.2")], false);
+        VersionFilter::new(&options).namespace(&mut ns).unwrap();
+        assert_eq!(ns.classes.len(), 2);
+        let popover = ns.classes.get("Popover").unwrap();
+        assert!(kwargs(&popover.constructor).is_empty());
+        let widget = ns.classes.get("Widget").unwrap();
+        assert_eq!(kwargs(&widget.constructor).len(), 2);
+    }
+
+    fn written(gir: &str, options: &Options) -> String {
+        let ns = analyze_gir(gir);
+        let symbols = SymbolIndex::default();
+        let mut out = Vec::new();
+        PythonGenerator::new(&mut out, options, &symbols)
+            .write_namespace(ns)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn typing_extensions_import() {
+        let plain = r#"<repository><namespace name="Test" version="1.0">
+            <class name="Widget"/>
+        </namespace></repository>"#;
+        let deprecated = plain.replace("<class ", r#"<class deprecated="1" "#);
+        let is_final = plain.replace("<class ", r#"<class final="1" "#);
+        let is_abstract = plain.replace("<class ", r#"<class abstract="1" "#);
+        let import = "import typing_extensions\n";
+
+        let py38 = Options::default();
+        let py37 = Options {
+            python_version: Version(vec![3, 7]),
+            ..Default::default()
+        };
+        assert!(!written(plain, &py38).contains(import));
+        assert!(written(&deprecated, &py38).contains(import));
+        assert!(written(&is_abstract, &py38).contains(import));
+        assert!(!written(&is_final, &py38).contains(import));
+        assert!(written(&is_final, &py37).contains(import));
     }
 }
@@ -0,0 +1,20 @@
+import asyncio
+from typing import Any, Callable, Optional
+
+from gi.repository import GLib
+
+# This file was hand made, based directly of PyGObject events.py
+
+class GLibEventLoop(asyncio.SelectorEventLoop):
+    def __init__(self, main_context: GLib.MainContext) -> None: ...
+    def run_forever(self) -> None: ...
+    def time(self) -> float: ...
+    def call_at(self, when: float, callback: Callable[..., object], *args: Any, context: Any = None) -> asyncio.TimerHandle: ...
+    def add_signal_handler(self, sig: int, callback: Callable[..., object], *args: Any) -> None: ...
+    def remove_signal_handler(self, sig: int) -> bool: ...
+
+class GLibEventLoopPolicy(asyncio.AbstractEventLoopPolicy):
+    def __init__(self) -> None: ...
+    def get_event_loop(self) -> GLibEventLoop: ...
+    def set_event_loop(self, loop: Optional[asyncio.AbstractEventLoop]) -> None: ...
+    def new_event_loop(self) -> GLibEventLoop: ...
@@ -1,9 +1,16 @@
-from typing import Optional, Union
+from typing import Literal, Optional, Union, overload
 
-# This file was hand made, based directly of PyGObject __init__.py
+# This file was hand made, based directly of PyGObject __init__.py. The
+# overloads of require_version are added by gengir, one for each namespace
+# with GIR files
+
+version_info: tuple[int, int, int]
+__version__: str
+
+class PyGIWarning(Warning): ...
+class PyGIDeprecationWarning(DeprecationWarning): ...
 
 def check_version(version: Union[str, tuple[int, int, int]]) -> None: ...
-def require_version(namespace: str, version: str) -> None: ...
 def require_versions(requires: dict[str, str]) -> None: ...
-def get_required_version(namespace: str) -> None: ...
+def get_required_version(namespace: str) -> Optional[str]: ...
 def require_foreign(namespace: str, symbol: Optional[str] = None) -> None: ...
@@ -0,0 +1,14 @@
+from typing import Any, Optional
+
+# This file was hand made, based directly of PyGObject module.py
+
+class IntrospectionModule:
+    """The module of a namespace, without the overrides"""
+    _namespace: str
+    _version: str
+    __path__: str
+    def __init__(self, namespace: str, version: Optional[str] = None) -> None: ...
+    def __getattr__(self, name: str) -> Any: ...
+    def __dir__(self) -> list[str]: ...
+
+def get_introspection_module(namespace: str) -> IntrospectionModule: ...
@@ -0,0 +1,35 @@
+import types
+from typing import Any, Callable, Mapping, Optional, Sequence, TypeVar
+
+from gi.module import IntrospectionModule
+
+# This file was hand made, based directly of PyGObject overrides/__init__.py
+
+_T = TypeVar("_T")
+_F = TypeVar("_F", bound=Callable[..., Any])
+
+class OverridesProxyModule(types.ModuleType):
+    def __init__(self, introspection_module: IntrospectionModule) -> None: ...
+    def __getattr__(self, name: str) -> Any: ...
+    def __dir__(self) -> list[str]: ...
+
+def load_overrides(introspection_module: IntrospectionModule) -> types.ModuleType: ...
+def override(type_: _T) -> _T: ...
+def deprecated(fn: _F, replacement: str) -> _F: ...
+def deprecated_attr(namespace: str, attr: str, replacement: str) -> None: ...
+def deprecated_init(
+    super_init_func: Callable[..., None],
+    arg_names: Sequence[str],
+    ignore: Sequence[str] = ...,
+    deprecated_aliases: Mapping[str, str] = ...,
+    deprecated_defaults: Mapping[str, Any] = ...,
+    category: type[Warning] = ...,
+    stacklevel: int = ...,
+) -> Callable[..., None]: ...
+def strip_boolean_result(
+    method: Callable[..., Any],
+    exc_type: Optional[type[BaseException]] = None,
+    exc_str: Optional[str] = None,
+    fail_ret: Any = None,
+) -> Callable[..., Any]: ...
+def wrap_list_store_sort_func(func: Callable[..., int]) -> Callable[..., int]: ...
@@ -0,0 +1,6 @@
+from typing import Any
+
+# This file was hand made. The namespaces that weren't generated are still
+# importable, but untyped
+
+def __getattr__(name: str) -> Any: ...
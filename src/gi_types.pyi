@@ -0,0 +1,17 @@
+from typing import Any
+
+# This file was hand made, based directly of PyGObject types.py
+
+def snake_case(name: str) -> str: ...
+
+class MetaClassHelper: ...
+
+class GObjectMeta(type, MetaClassHelper):
+    def __init__(cls, name: str, bases: tuple[type, ...], dict_: dict[str, Any]) -> None: ...
+    def mro(cls) -> list[type]: ...
+
+class StructMeta(type, MetaClassHelper):
+    def __init__(cls, name: str, bases: tuple[type, ...], dict_: dict[str, Any]) -> None: ...
+
+def mro(C: type) -> list[type]: ...
+def nothing(*args: Any, **kwargs: Any) -> None: ...
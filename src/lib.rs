@@ -0,0 +1,13 @@
+//! Generates PEP 561 stubs for the GObject introspection library. The
+//! [`Analyzer`](analyzer::Analyzer) reads the GIR files, a
+//! [`Pipeline`](passes::Pipeline) of passes changes the declarations, and the
+//! [`PythonGenerator`](generation::PythonGenerator) writes them
+
+pub mod analyzer;
+pub mod declarations;
+pub mod docs;
+pub mod foreign;
+pub mod generation;
+pub mod overrides;
+pub mod package;
+pub mod passes;
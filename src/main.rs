@@ -1,58 +1,125 @@
-mod analyzer;
-mod declarations;
-mod generation;
-mod overrides;
-
 use std::{
+    collections::HashMap,
     ffi::OsString,
-    fs::{self, File},
-    io::{self, BufWriter, Write},
+    fs::File,
+    io::{self, BufWriter},
     os::unix::prelude::OsStringExt,
-    path::PathBuf,
-    process::Command,
+    path::{Path, PathBuf},
+    process::{self, Command},
 };
 
-use analyzer::Analyzer;
 use clap::Parser;
 //use overrides::apply_overrides;
 
-use crate::{generation::PythonGenerator, overrides::apply_overrides};
+use gengir::{
+    analyzer::{installed_girs, read_includes, resolve_versions, Analyzer, Gir},
+    declarations::Version,
+    docs::{Format, SymbolIndex},
+    foreign::ForeignTypes,
+    generation::{DocStyle, Options, PythonGenerator, VersionFilter},
+    overrides::{BuiltinOverrides, ExtraStubs, OverrideFile, PyGObjectOverrides},
+    package::create_stub_tree,
+    passes::{Pipeline, Stage},
+};
 
 #[derive(Parser, Debug)]
 #[clap(about, version, author)]
 struct Args {
     /// Modules to generate typing stubs for. (e.g. Adw-1 GtkSource-5)
-    modules: Vec<String>,
+    modules: Vec<Gir>,
 
     // Directory to store the package typings. $site-packages/gi-stubs by default
     #[clap(short, long, parse(from_os_str))]
     out_dir: Option<PathBuf>,
 
-    /// Exclude docstrings in the typings
+    /// Exclude docstrings in the typings, same as --doc-style none
     #[clap(short, long)]
     no_docs: bool,
-}
 
-fn create_stub_tree(dir: &PathBuf) -> io::Result<()> {
-    let repo = dir.join("repository");
+    /// How much of the documentation goes in the docstrings. summary shortens the docs of functions to their first sentence
+    #[clap(long, arg_enum, default_value = "summary")]
+    doc_style: DocStyle,
 
-    if let Err(e) = fs::create_dir_all(&repo) {
-        match e.kind() {
-            io::ErrorKind::AlreadyExists => (),
-            _ => return Err(e),
-        }
-    };
+    /// Markup of the docstrings
+    #[clap(long, arg_enum, default_value = "sphinx")]
+    doc_format: Format,
 
-    let mut py_typed = File::create(dir.join("py.typed"))?;
-    py_typed.write(b"partial\n")?;
+    /// List the declarations left out of the typings
+    #[clap(short, long)]
+    verbose: bool,
 
-    let mut init = File::create(dir.join("__init__.pyi"))?;
-    init.write(include_bytes!("gi_init.pyi"))?;
+    /// Version of a module to keep when the others need different ones, leaving out what needs the rest (e.g. Gtk-4.0)
+    #[clap(long)]
+    prefer: Vec<Gir>,
 
-    let mut repo_init = File::create(repo.join("__init__.pyi"))?;
-    repo_init.write(b"")?;
+    /// Python version targeted by the typings
+    #[clap(long, default_value = "3.8")]
+    python_version: Version,
 
-    Ok(())
+    /// Leave out anything newer than this version of a module (e.g. Gtk=4.6)
+    #[clap(long, parse(try_from_str = parse_target_version))]
+    target_version: Vec<(String, Version)>,
+
+    /// Mark APIs newer than --target-version as deprecated instead of leaving them out
+    #[clap(long)]
+    warn_newer: bool,
+
+    /// Add comments with the C symbol and header position of each declaration
+    #[clap(long)]
+    provenance: bool,
+
+    /// Map a foreign record to a python class, besides the pycairo ones (e.g. cairo.Context=cairo.Context)
+    #[clap(long, parse(try_from_str = parse_foreign))]
+    foreign: Vec<(String, String)>,
+
+    /// Apply the overrides in a TOML file, after the ones in ./gengir-overrides.toml
+    #[clap(long, parse(from_os_str))]
+    overrides: Vec<PathBuf>,
+
+    /// Merge the hand written stubs in this directory, like DIR/Gtk.pyi for Gtk
+    #[clap(long, parse(from_os_str))]
+    extra_stubs: Option<PathBuf>,
+
+    /// Let gi.require_version take any namespace, for typelibs without a GIR file. Wrong versions aren't caught then
+    #[clap(long)]
+    loose_require_version: bool,
+
+    /// Take the signatures from the python overrides of the installed PyGObject
+    #[clap(long)]
+    pygobject: bool,
+
+    /// Same as --pygobject, with the overrides in this directory (e.g. site-packages/gi/overrides)
+    #[clap(long, parse(from_os_str))]
+    pygobject_overrides: Option<PathBuf>,
+}
+
+/// Override file applied by default, if found in the current directory
+const PROJECT_OVERRIDES: &str = "gengir-overrides.toml";
+
+fn parse_target_version(s: &str) -> Result<(String, Version), String> {
+    let (module, version) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected MODULE=VERSION, found `{}`", s))?;
+    Ok((module.to_string(), version.parse()?))
+}
+
+fn parse_foreign(s: &str) -> Result<(String, String), String> {
+    let (record, class) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected RECORD=CLASS, found `{}`", s))?;
+    if !record.contains('.') || !class.contains('.') {
+        return Err(format!(
+            "expected names with a dot, like cairo.Context, found `{}`",
+            s
+        ));
+    }
+    Ok((record.to_string(), class.to_string()))
+}
+
+/// Prints the error and exits, for mistakes in the input files
+fn fail(message: impl std::fmt::Display) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1)
 }
 
 fn get_sitepackages() -> PathBuf {
@@ -68,29 +135,103 @@ fn get_sitepackages() -> PathBuf {
 fn main() -> io::Result<()> {
     let cli = Args::parse();
 
+    let resolution =
+        resolve_versions(&cli.modules, &cli.prefer, read_includes).unwrap_or_else(|e| fail(e));
+    for (gir, loser) in &resolution.left_out {
+        if gir == loser {
+            eprintln!("leaving out {}, another version was preferred", gir);
+        } else {
+            eprintln!("leaving out {}, it needs {}", gir, loser);
+        }
+    }
+
     let out_dir = cli
         .out_dir
         .unwrap_or_else(|| get_sitepackages().join("gi-stubs"));
 
-    println!("creating gi-stubs tree in {}", out_dir.display());
+    let doc_style = if cli.no_docs {
+        DocStyle::None
+    } else {
+        cli.doc_style
+    };
 
-    create_stub_tree(&out_dir)?;
+    let options = Options {
+        python_version: cli.python_version,
+        target_versions: cli.target_version.into_iter().collect::<HashMap<_, _>>(),
+        warn_newer: cli.warn_newer,
+        doc_style,
+        doc_format: cli.doc_format,
+        provenance: cli.provenance,
+    };
 
-    let mut analyzer = Analyzer::new(cli.no_docs);
+    let project_overrides = Path::new(PROJECT_OVERRIDES);
+    let override_files = project_overrides
+        .exists()
+        .then(|| project_overrides.to_path_buf())
+        .into_iter()
+        .chain(cli.overrides)
+        .map(|path| OverrideFile::load(&path).unwrap_or_else(|e| fail(e)))
+        .collect::<Vec<_>>();
+
+    let extra_stubs = cli
+        .extra_stubs
+        .map(|dir| ExtraStubs::new(&dir).unwrap_or_else(|e| fail(e)));
+    let pygobject_overrides = (cli.pygobject || cli.pygobject_overrides.is_some()).then(|| {
+        PyGObjectOverrides::new(cli.pygobject_overrides.clone()).unwrap_or_else(|e| fail(e))
+    });
+
+    let mut analyzer = Analyzer::new(doc_style == DocStyle::None, cli.verbose);
+
+    for gir in resolution.roots {
+        analyzer
+            .analyze_repository(&gir.name, &gir.version)
+            .unwrap_or_else(|e| fail(e));
+    }
 
-    for gir in cli.modules {
-        let split: Vec<_> = gir.split('-').take(2).collect();
-        if split.len() == 2 {
-            analyzer.analyze_repository(split[0], split[1]);
-        }
+    // built before the overrides, so docs can still refer to C symbols that
+    // they rename or remove
+    let symbols = SymbolIndex::new(&analyzer.namespaces);
+
+    let mut foreign = ForeignTypes::default();
+    for (record, class) in &cli.foreign {
+        foreign.insert(record, class);
+    }
+
+    let mut pipeline = Pipeline::default();
+    pipeline.add(Stage::Resolution, foreign.clone());
+    // the packs are written by hand, so they go over what's read from PyGObject
+    if let Some(pygobject) = pygobject_overrides {
+        pipeline.add(Stage::Overrides, pygobject);
     }
+    pipeline.add(Stage::Overrides, BuiltinOverrides::new(cli.verbose));
+    for file in override_files {
+        pipeline.add(Stage::Overrides, file);
+    }
+    if let Some(stubs) = extra_stubs {
+        pipeline.add(Stage::Overrides, stubs);
+    }
+    pipeline.add(Stage::Filtering, VersionFilter::new(&options));
+
+    // every namespace goes through the passes before anything is written,
+    // so a mistake in the overrides doesn't leave the stubs half updated
+    let namespaces: Vec<_> = analyzer
+        .namespaces
+        .into_iter()
+        .filter(|ns| !foreign.is_foreign(&ns.name))
+        .map(|mut ns| {
+            pipeline.run(&mut ns).unwrap_or_else(|e| fail(e));
+            ns
+        })
+        .collect();
+
+    println!("creating gi-stubs tree in {}", out_dir.display());
 
-    for mut ns in analyzer.namespaces {
-        apply_overrides(&mut ns);
+    create_stub_tree(&out_dir, &installed_girs(), cli.loose_require_version)?;
 
-        let py = File::create(&out_dir.join("repository").join(ns.name.clone() + ".pyi"))?;
+    for ns in namespaces {
+        let py = File::create(out_dir.join("repository").join(ns.name.clone() + ".pyi"))?;
         let mut buf = BufWriter::new(py);
-        let mut gen = PythonGenerator::new(&mut buf);
+        let mut gen = PythonGenerator::new(&mut buf, &options, &symbols);
         gen.write_namespace(ns)?;
     }
 
@@ -0,0 +1,440 @@
+//! Overrides written by hand in a TOML file. The tables are namespaces, and
+//! their keys the declarations to change, with a dot between a class and its
+//! members:
+//!
+//! ```toml
+//! [Gio."Application.run"]
+//! signature = "def run(self, argv: typing.Optional[list[str]] = None) -> int"
+//!
+//! [Gtk.Widget]
+//! bases = ["typing.Generic[T]"]
+//! add = ["def __iter__(self) -> typing.Iterator[Widget]: ..."]
+//! ```
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::Deserialize;
+
+use super::{
+    python::{parse_def, parse_member, Member},
+    Transofrmer,
+};
+use crate::{
+    declarations::{Class, Function, FunctionKind, Namespace, Param, Type},
+    passes::Pass,
+};
+
+/// What to change in a declaration. Everything is optional, but not every
+/// change applies to every kind of declaration
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Override {
+    /// replaces the parameters and return type of a function, written like
+    /// `def name(self, param: int) -> str`
+    signature: Option<String>,
+    /// changes the types of some parameters of a function
+    params: BTreeMap<String, String>,
+    /// changes the return type of a function
+    returns: Option<String>,
+    rename: Option<String>,
+    remove: bool,
+    /// methods and fields added to a class
+    add: Vec<String>,
+    /// types added to the bases of a class
+    bases: Vec<String>,
+}
+
+/// An override file, loaded from `--overrides` or `gengir-overrides.toml`
+#[derive(Debug)]
+pub struct OverrideFile {
+    path: String,
+    namespaces: BTreeMap<String, BTreeMap<String, Override>>,
+}
+
+impl Override {
+    fn only_for(&self, what: &str, allowed: &[&str]) -> Result<(), String> {
+        let used = [
+            ("signature", self.signature.is_some()),
+            ("params", !self.params.is_empty()),
+            ("returns", self.returns.is_some()),
+            ("add", !self.add.is_empty()),
+            ("bases", !self.bases.is_empty()),
+        ];
+
+        match used.iter().find(|(k, used)| *used && !allowed.contains(k)) {
+            Some((key, _)) => Err(format!("`{}` can't be used on {}", key, what)),
+            None => Ok(()),
+        }
+    }
+
+    fn apply_function(&self, mut func: Function) -> Result<Function, String> {
+        if let Some(signature) = &self.signature {
+            let mut new = parse_def(signature)?;
+
+            // keep the docs of the parameters that are still there
+            for param in &mut new.parameters {
+                if let Param::Named { name, doc, .. }
+                | Param::Variadic { name, doc, .. }
+                | Param::Keywords { name, doc, .. } = param
+                {
+                    *doc = func.parameters.iter().find_map(|p| match p {
+                        Param::Named { name: n, doc, .. }
+                        | Param::Variadic { name: n, doc, .. }
+                        | Param::Keywords { name: n, doc, .. }
+                            if n == name =>
+                        {
+                            doc.clone()
+                        }
+                        _ => None,
+                    });
+                }
+            }
+
+            func.name = new.name;
+            func.parameters = new.parameters;
+            func.return_type = new.return_type;
+            // the callback parameters are likely gone
+            func.finish = None;
+            if func.kind != FunctionKind::Static {
+                func.kind = match new.kind {
+                    FunctionKind::Static => FunctionKind::StaticMethod,
+                    kind => kind,
+                };
+            }
+        }
+
+        for (param, new_typ) in &self.params {
+            let typ = func.parameters.iter_mut().find_map(|p| match p {
+                Param::Named {
+                    name,
+                    typ,
+                    optional,
+                    ..
+                } if name == param => {
+                    *optional = false;
+                    Some(typ)
+                }
+                Param::Variadic { name, typ, .. } | Param::Keywords { name, typ, .. }
+                    if name == param =>
+                {
+                    Some(typ)
+                }
+                _ => None,
+            });
+
+            match typ {
+                Some(typ) => *typ = Type::Primitive(new_typ.clone()),
+                None => return Err(format!("no parameter `{}`", param)),
+            }
+        }
+
+        if let Some(returns) = &self.returns {
+            func.return_type = Type::Primitive(returns.clone());
+        }
+        if let Some(name) = &self.rename {
+            func.name = name.clone();
+        }
+
+        Ok(func)
+    }
+
+    fn apply_class(&self, mut class: Class) -> Result<Class, String> {
+        for base in &self.bases {
+            class.bases.push(Type::Primitive(base.clone()));
+        }
+
+        for member in &self.add {
+            match parse_member(member)? {
+                Member::Method(method) => {
+                    let name = method.name.clone();
+                    if !class.methods.insert(method) {
+                        return Err(format!("there's already a method `{}`", name));
+                    }
+                }
+                Member::Field(field) => {
+                    if class.fields.iter().any(|f| f.name == field.name) {
+                        return Err(format!("there's already a field `{}`", field.name));
+                    }
+                    class.fields.push(field);
+                }
+            }
+        }
+
+        if let Some(name) = &self.rename {
+            class.name = name.clone();
+        }
+
+        Ok(class)
+    }
+
+    fn apply(&self, t: &mut Transofrmer, target: &str) -> Result<(), String> {
+        if let Some((class, member)) = target.split_once('.') {
+            self.only_for("methods", &["signature", "params", "returns"])?;
+
+            return match member {
+                "__init__" if self.remove || self.rename.is_some() => {
+                    Err("the constructor can't be renamed or removed".to_string())
+                }
+                "__init__" => t.transform_constructor(class, |c| self.apply_function(c)),
+                _ if self.remove => t.remove_method(class, member),
+                _ => t.transform_method(class, member, |m| self.apply_function(m)),
+            };
+        }
+
+        if t.has_class(target) {
+            self.only_for("classes", &["add", "bases"])?;
+
+            if self.remove {
+                return t.remove_class(target);
+            }
+            t.transform_class(target, |c| self.apply_class(c))?;
+            if let Some(new_name) = &self.rename {
+                t.0.for_each_type(&mut |typ| match typ {
+                    Type::LocalClass(name) if name == target => *name = new_name.clone(),
+                    _ => {}
+                });
+            }
+            return Ok(());
+        }
+
+        if t.function(target).is_some() {
+            self.only_for("functions", &["signature", "params", "returns"])?;
+
+            if self.remove {
+                t.remove_function(target);
+                return Ok(());
+            }
+            let func = t.function(target).unwrap();
+            *func = self.apply_function(func.clone())?;
+            return Ok(());
+        }
+
+        if t.name_of(target).is_some() {
+            self.only_for("constants and enums", &[])?;
+
+            if self.remove {
+                t.remove_name(target);
+            } else if let Some(new_name) = &self.rename {
+                *t.name_of(target).unwrap() = new_name.clone();
+            }
+            return Ok(());
+        }
+
+        Err("no such declaration".to_string())
+    }
+}
+
+impl OverrideFile {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let display = path.display().to_string();
+        let source = fs::read_to_string(path).map_err(|e| format!("{}: {}", display, e))?;
+        let namespaces = toml::from_str(&source).map_err(|e| format!("{}: {}", display, e))?;
+
+        Ok(Self {
+            path: display,
+            namespaces,
+        })
+    }
+
+    /// Applies the overrides of the namespace. Namespaces not in the file are
+    /// left as is, but the declarations named in it must exist
+    pub fn apply(&self, ns: &mut Namespace) -> Result<(), String> {
+        let overrides = match self.namespaces.get(&ns.name) {
+            Some(overrides) => overrides,
+            None => return Ok(()),
+        };
+        let name = ns.name.clone();
+        let mut t = Transofrmer(ns);
+
+        for (target, o) in overrides {
+            o.apply(&mut t, target)
+                .map_err(|e| format!("{}: {}.{}: {}", self.path, name, target, e))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Pass for OverrideFile {
+    fn namespace(&mut self, ns: &mut Namespace) -> Result<(), String> {
+        self.apply(ns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::analyze_gir;
+
+    const GIR: &str = r#"<repository><namespace name="Test" version="1.0">
+        <constant name="MAX" value="10"><type name="gint"/></constant>
+        <enumeration name="Mode"><member name="on" value="1"/></enumeration>
+        <function name="init">
+            <return-value><type name="gboolean"/></return-value>
+            <parameters><parameter name="argc"><type name="gint"/></parameter></parameters>
+        </function>
+        <class name="Widget">
+            <method name="show">
+                <return-value><type name="none"/></return-value>
+                <parameters>
+                    <instance-parameter name="self"><type name="Widget"/></instance-parameter>
+                    <parameter name="animate"><doc>Whether to animate</doc><type name="gboolean"/></parameter>
+                </parameters>
+            </method>
+        </class>
+        <class name="Button" parent="Widget"/>
+    </namespace></repository>"#;
+
+    fn apply(overrides: &str) -> Result<Namespace, String> {
+        let mut ns = analyze_gir(GIR);
+        let file = OverrideFile {
+            path: String::from("test.toml"),
+            namespaces: toml::from_str(overrides).unwrap(),
+        };
+        file.apply(&mut ns)?;
+        Ok(ns)
+    }
+
+    fn error(overrides: &str) -> String {
+        apply(overrides).err().expect("the overrides should fail")
+    }
+
+    fn show(ns: &Namespace) -> &Function {
+        let widget = ns.classes.get("Widget").unwrap();
+        &widget.methods[widget.find_method("show").unwrap()]
+    }
+
+    fn param_type<'a>(func: &'a Function, param: &str) -> &'a Type {
+        func.parameters
+            .iter()
+            .find_map(|p| match p {
+                Param::Named { name, typ, .. } if name == param => Some(typ),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn other_namespaces() {
+        assert!(apply("[Other.Missing]\nremove = true").is_ok());
+    }
+
+    #[test]
+    fn rename() {
+        let ns = apply(
+            r#"[Test.Widget]
+            rename = "View"
+            [Test.init]
+            rename = "setup"
+            [Test.MAX]
+            rename = "LIMIT""#,
+        )
+        .unwrap();
+
+        assert!(ns.classes.contains("View") && !ns.classes.contains("Widget"));
+        let button = ns.classes.get("Button").unwrap();
+        assert_eq!(button.bases[0].to_string(), "View");
+        assert_eq!(ns.functions[0].name, "setup");
+        assert_eq!(ns.constants[0].name, "LIMIT");
+    }
+
+    #[test]
+    fn remove() {
+        let ns = apply(
+            r#"[Test."Widget.show"]
+            remove = true
+            [Test.Button]
+            remove = true
+            [Test.Mode]
+            remove = true"#,
+        )
+        .unwrap();
+
+        assert!(ns.classes.get("Widget").unwrap().methods.is_empty());
+        assert!(!ns.classes.contains("Button"));
+        assert!(ns.enums.is_empty());
+    }
+
+    #[test]
+    fn params_and_returns() {
+        let ns = apply(
+            r#"[Test."Widget.show"]
+            params = { animate = "typing.Literal[True]" }
+            returns = "bool""#,
+        )
+        .unwrap();
+
+        let show = show(&ns);
+        assert_eq!(
+            param_type(show, "animate").to_string(),
+            "typing.Literal[True]"
+        );
+        assert_eq!(show.return_type.to_string(), "bool");
+    }
+
+    #[test]
+    fn signature() {
+        let ns = apply(
+            r#"[Test."Widget.show"]
+            signature = "def show(self, animate: bool, delay: int) -> None""#,
+        )
+        .unwrap();
+
+        let show = show(&ns);
+        assert_eq!(show.kind, FunctionKind::Method);
+        assert_eq!(show.parameters.len(), 3);
+        // the docs of the parameters that are still there are kept
+        let doc = show.parameters.iter().find_map(|p| match p {
+            Param::Named { name, doc, .. } if name == "animate" => doc.clone(),
+            _ => None,
+        });
+        assert_eq!(doc.as_deref(), Some("Whether to animate"));
+    }
+
+    #[test]
+    fn add_and_bases() {
+        let ns = apply(
+            r#"[Test.Widget]
+            bases = ["typing.Generic[T]"]
+            add = ["def __len__(self) -> int", "name: str"]"#,
+        )
+        .unwrap();
+
+        let widget = ns.classes.get("Widget").unwrap();
+        assert_eq!(widget.bases[0].to_string(), "typing.Generic[T]");
+        assert!(widget.find_method("__len__").is_some());
+        assert_eq!(widget.fields[0].name, "name");
+    }
+
+    #[test]
+    fn missing_targets() {
+        assert_eq!(
+            error("[Test.Missing]\nremove = true"),
+            "test.toml: Test.Missing: no such declaration"
+        );
+        assert!(
+            error("[Test.\"Widget.hide\"]\nreturns = \"int\"").ends_with("no method `Widget.hide`")
+        );
+        assert!(error("[Test.\"Missing.show\"]\nremove = true").ends_with("no class `Missing`"));
+        assert!(
+            error("[Test.\"Widget.show\"]\nparams = { x = \"int\" }").ends_with("no parameter `x`")
+        );
+    }
+
+    #[test]
+    fn misused_keys() {
+        assert!(error("[Test.Widget]\nreturns = \"int\"")
+            .ends_with("`returns` can't be used on classes"));
+        assert!(
+            error("[Test.init]\nbases = [\"A\"]").ends_with("`bases` can't be used on functions")
+        );
+        assert!(error("[Test.MAX]\nadd = [\"x: int\"]")
+            .ends_with("`add` can't be used on constants and enums"));
+        assert!(error("[Test.\"Widget.__init__\"]\nremove = true")
+            .ends_with("the constructor can't be renamed or removed"));
+        assert!(error("[Test.Widget]\nadd = [\"def show(self) -> None\"]")
+            .ends_with("there's already a method `show`"));
+        assert!(error("[Test.\"Widget.show\"]\nsignature = \"show(self)\"")
+            .contains("expected a function definition"));
+    }
+}
@@ -0,0 +1,247 @@
+mod file;
+mod pygobject;
+mod python;
+mod stubs;
+
+use if_chain::if_chain;
+
+use crate::{
+    declarations::{Class, Function, Namespace, Type},
+    passes::Pass,
+};
+
+pub use self::{file::OverrideFile, pygobject::PyGObjectOverrides, stubs::ExtraStubs};
+
+use self::stubs::merge_pack;
+
+#[macro_export]
+macro_rules! typ {
+    (any) => {
+        Type::Any
+    };
+    ($prim:ident) => {
+        Type::Primitive(String::from(stringify!($prim)))
+    };
+    ($prim:expr) => {
+        Type::Primitive(String::from($prim))
+    };
+    (.$cls:ident) => {
+        Type::LocalClass(String::from(stringify!($prim)))
+    };
+    ($mod:ident.$cls:ident) => {
+        Type::ExternalClass {
+            module: String::from(stringify!($mod)),
+            name: String::from(stringify!($cls)),
+        }
+    };
+}
+
+/// This applies overrides based on the
+/// [`gi.overrides`](https://gitlab.gnome.org/GNOME/pygobject/-/tree/master/gi/overrides)
+/// module in PyGObject and some trial and error. Older versions of the
+/// libraries may lack what's overridden, which is returned as an error
+pub fn apply_overrides(ns: &mut Namespace) -> Result<(), String> {
+    let name = ns.name.clone();
+    let mut t = Transofrmer(ns);
+
+    if let "Gio" = name.as_str() {
+        t.transform_method("Application", "run", |run| {
+            Ok(run.clear_parameters().add_self_param().add_named_param(
+                "argv",
+                typ!("list[str]"),
+                true,
+                "The process command line arguments. Uses `sys.argv` if None",
+            ))
+        })?;
+    }
+    // if let "Gtk" = name.as_str() {
+    //     t.transform_method("Button", "__init__", |run| {
+    //         run.clear_parameters()
+    //             .add_self_param()
+    //             .add_star_param()
+    //             .add_named_param(
+    //                 "label",
+    //                 typ!(str),
+    //                 false,
+    //                 "Text displayed inside the button",
+    //             )
+    //             .add_named_param("use_stock", typ!(bool), false, None)
+    //             .add_named_param("use_underline", typ!(bool), false, None)
+    //     })?;
+    // }
+
+    Ok(())
+}
+
+/// Stubs merged into the namespaces, for what PyGObject adds in python. The
+/// ones with a major version only apply to that version of the namespace, and
+/// are merged after the ones for every version
+const PACKS: &[(&str, Option<u32>, &str)] = &[
+    ("GLib", None, include_str!("packs/GLib.pyi")),
+    ("GObject", None, include_str!("packs/GObject.pyi")),
+    ("Gio", None, include_str!("packs/Gio.pyi")),
+    ("Gdk", None, include_str!("packs/Gdk.pyi")),
+    ("Gdk", Some(3), include_str!("packs/Gdk-3.0.pyi")),
+    ("Gtk", None, include_str!("packs/Gtk.pyi")),
+    ("Gtk", Some(3), include_str!("packs/Gtk-3.0.pyi")),
+    ("Gtk", Some(4), include_str!("packs/Gtk-4.0.pyi")),
+];
+
+/// The packs for a namespace, in the order of [`PACKS`]
+fn packs(ns: &Namespace) -> Vec<&'static str> {
+    let major = ns.version.as_ref().and_then(|v| v.0.first().copied());
+    PACKS
+        .iter()
+        .filter(|(name, version, _)| *name == ns.name && (version.is_none() || *version == major))
+        .map(|(_, _, pack)| *pack)
+        .collect()
+}
+
+/// The overrides of [`apply_overrides`] and the packs of stubs, as a pass
+pub struct BuiltinOverrides {
+    verbose: bool,
+}
+
+impl BuiltinOverrides {
+    pub fn new(verbose: bool) -> Self {
+        Self { verbose }
+    }
+
+    /// Prints what couldn't be applied to the namespace in verbose mode
+    fn log(&self, ns: &Namespace, message: impl std::fmt::Display) {
+        if self.verbose {
+            eprintln!("- {}: {}", ns.name, message);
+        }
+    }
+}
+
+impl Pass for BuiltinOverrides {
+    fn namespace(&mut self, ns: &mut Namespace) -> Result<(), String> {
+        if let Err(e) = apply_overrides(ns) {
+            self.log(ns, format!("skipped the built-in overrides: {}", e));
+        }
+        for pack in packs(ns) {
+            for left_out in merge_pack(ns, pack)? {
+                self.log(ns, format!("left out the stubs of {}", left_out));
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Transofrmer<'a>(&'a mut Namespace);
+
+impl<'a> Transofrmer<'a> {
+    fn has_class(&self, class: &str) -> bool {
+        self.0.classes.contains(class)
+    }
+
+    fn transform_class(
+        &mut self,
+        class: &str,
+        tfn: impl FnOnce(Class) -> Result<Class, String>,
+    ) -> Result<(), String> {
+        let classes = &mut self.0.classes; // alias
+
+        // get the index where the class is placed
+        let origin = classes
+            .get_index_of(class)
+            .ok_or_else(|| format!("no class `{}`", class))?;
+        // swapping is faster than shifting
+        let class_decl = classes.swap_remove_index(origin).unwrap();
+        let class_decl = tfn(class_decl)?;
+        let name = class_decl.name.clone();
+
+        if !classes.insert(class_decl) {
+            return Err(format!("there's already a class `{}`", name));
+        }
+        // and put it back in place, maintaining the topological order
+        classes.swap_indices(classes.len() - 1, origin);
+        Ok(())
+    }
+
+    fn remove_class(&mut self, class: &str) -> Result<(), String> {
+        // shifting keeps the topological order
+        self.0
+            .classes
+            .shift_remove(class)
+            .then_some(())
+            .ok_or_else(|| format!("no class `{}`", class))
+    }
+
+    fn transform_method(
+        &mut self,
+        class: &str,
+        method: &str,
+        tfn: impl FnOnce(Function) -> Result<Function, String>,
+    ) -> Result<(), String> {
+        self.transform_class(class, |mut class_decl| {
+            let index = class_decl.find_method(method);
+            let methods = &mut class_decl.methods; // alias
+
+            if_chain! {
+                if let Some(index) = index;
+                if let Some(method_decl) = methods.swap_remove_index(index);
+                then {
+                    let method_decl = tfn(method_decl)?;
+                    let name = method_decl.name.clone();
+
+                    if !methods.insert(method_decl) {
+                        return Err(format!("there's already a method `{}.{}`", class, name));
+                    }
+                    methods.swap_indices(methods.len() - 1, index);
+                    Ok(class_decl)
+                } else {
+                    Err(format!("no method `{}.{}`", class, method))
+                }
+            }
+        })
+    }
+
+    fn remove_method(&mut self, class: &str, method: &str) -> Result<(), String> {
+        self.transform_class(class, |mut class_decl| {
+            match class_decl.find_method(method) {
+                Some(index) => {
+                    class_decl.methods.shift_remove_index(index);
+                    Ok(class_decl)
+                }
+                None => Err(format!("no method `{}.{}`", class, method)),
+            }
+        })
+    }
+
+    fn transform_constructor(
+        &mut self,
+        class: &str,
+        tfn: impl FnOnce(Function) -> Result<Function, String>,
+    ) -> Result<(), String> {
+        self.transform_class(class, |mut class_decl| {
+            class_decl.constructor = tfn(class_decl.constructor)?;
+            Ok(class_decl)
+        })
+    }
+
+    fn function(&mut self, name: &str) -> Option<&mut Function> {
+        self.0.functions.iter_mut().find(|f| f.name == name)
+    }
+
+    fn remove_function(&mut self, name: &str) -> bool {
+        let len = self.0.functions.len();
+        self.0.functions.retain(|f| f.name != name);
+        self.0.functions.len() != len
+    }
+
+    /// Finds a constant or an enum, which can only be renamed or removed
+    fn name_of(&mut self, name: &str) -> Option<&mut String> {
+        let constant = self.0.constants.iter_mut().map(|c| &mut c.name);
+        let enums = self.0.enums.iter_mut().map(|e| &mut e.name);
+        constant.chain(enums).find(|n| *n == name)
+    }
+
+    fn remove_name(&mut self, name: &str) -> bool {
+        let len = self.0.constants.len() + self.0.enums.len();
+        self.0.constants.retain(|c| c.name != name);
+        self.0.enums.retain(|e| e.name != name);
+        self.0.constants.len() + self.0.enums.len() != len
+    }
+}
@@ -0,0 +1,99 @@
+# Merged into the generated GLib stubs, based on gi/overrides/GLib.py and
+# gi/_error.py in PyGObject
+import typing
+
+class Error(RuntimeError):
+    """Raised when a function fails with a GError"""
+    domain: str
+    code: int
+    message: str
+    def __init__(self, message: str = "unknown error", domain: str = "pygi-error", code: int = 0) -> None: ...
+    def matches(self, domain: typing.Union[str, int], code: int) -> bool: ...
+    def copy(self) -> Error: ...
+    @staticmethod
+    def new_literal(domain: int, message: str, code: int) -> Error: ...
+
+GError = Error
+
+class Variant:
+    def __init__(self, format_string: str, value: typing.Any) -> None:
+        """Creates a variant of the type in the format string, like (si), from
+        a python value
+        """
+    def unpack(self) -> typing.Any:
+        """Converts the variant to a python value"""
+    def keys(self) -> list[typing.Any]: ...
+    def __getitem__(self, key: typing.Any) -> typing.Any: ...
+    def __len__(self) -> int: ...
+    def __bool__(self) -> bool: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __ne__(self, other: object) -> bool: ...
+    def __hash__(self) -> int: ...
+    @staticmethod
+    def new_tuple(*elements: Variant) -> Variant: ...
+    @staticmethod
+    def split_signature(signature: str) -> list[str]: ...
+
+class MainLoop:
+    def __init__(self, context: typing.Optional[MainContext] = None) -> None: ...
+    def run(self) -> None:
+        """Runs the loop until it quits, or until a KeyboardInterrupt"""
+
+class MainContext:
+    def iteration(self, may_block: bool = True) -> bool: ...
+
+class Source:
+    def __init__(self) -> None: ...
+    def prepare(self) -> tuple[bool, int]: ...
+    def check(self) -> bool: ...
+    def dispatch(self, callback: typing.Callable[..., bool], args: typing.Any) -> bool: ...
+    def set_callback(self, fn: typing.Callable[..., bool], *user_data: typing.Any) -> None: ...
+    def get_current_time(self) -> float: ...
+    priority: int
+    can_recurse: bool
+
+# defined in python by PyGObject
+class Idle(Source):
+    def __init__(self, priority: int = ...) -> None: ...
+
+# defined in python by PyGObject
+class Timeout(Source):
+    def __init__(self, interval: int = 0, priority: int = ...) -> None: ...
+
+class IOChannel:
+    def __init__(self, filedes: typing.Optional[int] = None, filename: typing.Optional[str] = None, mode: typing.Optional[str] = None, hwnd: typing.Optional[int] = None) -> None: ...
+    def read(self, max_count: int = -1) -> bytes: ...
+    def readline(self, size_hint: int = -1) -> str: ...
+    def readlines(self, size_hint: int = -1) -> list[str]: ...
+    def write(self, buf: typing.Union[str, bytes], buflen: int = -1) -> int: ...
+    def writelines(self, lines: typing.Iterable[typing.Union[str, bytes]]) -> None: ...
+    def seek(self, offset: int, whence: int = 0) -> IOStatus: ...
+    def add_watch(self, condition: IOCondition, callback: typing.Callable[..., bool], *user_data: typing.Any, priority: int = ...) -> int: ...
+    def __iter__(self) -> IOChannel: ...
+    def __next__(self) -> str: ...
+
+class PollFD:
+    def __init__(self, fd: int, events: int) -> None: ...
+
+def idle_add(function: typing.Callable[..., typing.Any], *user_data: typing.Any, priority: int = ...) -> int:
+    """Calls the function whenever there's nothing else to do, until it
+    returns a false value
+    """
+def timeout_add(interval: int, function: typing.Callable[..., typing.Any], *user_data: typing.Any, priority: int = ...) -> int:
+    """Calls the function every interval milliseconds, until it returns a
+    false value
+    """
+def timeout_add_seconds(interval: int, function: typing.Callable[..., typing.Any], *user_data: typing.Any, priority: int = ...) -> int: ...
+@typing.overload
+def io_add_watch(channel: typing.Union[IOChannel, int, typing.Any], priority: int, condition: IOCondition, callback: typing.Callable[..., bool], *user_data: typing.Any) -> int: ...
+@typing.overload
+def io_add_watch(channel: typing.Union[IOChannel, int, typing.Any], condition: IOCondition, callback: typing.Callable[..., bool], *user_data: typing.Any, priority: int = ...) -> int: ...
+@typing.overload
+def child_watch_add(priority: int, pid: int, function: typing.Callable[..., typing.Any], *data: typing.Any) -> int: ...
+@typing.overload
+def child_watch_add(pid: int, function: typing.Callable[..., typing.Any], *data: typing.Any, priority: int = ...) -> int: ...
+def unix_signal_add(priority: int, signum: int, handler: typing.Callable[..., bool], *user_data: typing.Any) -> int: ...
+def source_remove(tag: int) -> bool: ...
+
+glib_version: tuple[int, int, int]
+pyglib_version: tuple[int, int, int]
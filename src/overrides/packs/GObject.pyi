@@ -0,0 +1,227 @@
+# Merged into the generated GObject stubs, based on gi/overrides/GObject.py,
+# gi/_propertyhelper.py and gi/_signalhelper.py in PyGObject
+import builtins
+import typing
+
+_T = typing.TypeVar("_T")
+
+# defined in python by PyGObject
+class GType:
+    name: str
+    pytype: typing.Optional[type]
+    parent: GType
+    fundamental: GType
+    depth: int
+    children: list[GType]
+    interfaces: list[GType]
+    def __init__(self, type_: typing.Any) -> None: ...
+    @staticmethod
+    def from_name(name: str) -> GType: ...
+    def is_a(self, type_: typing.Any) -> bool: ...
+    def is_abstract(self) -> bool: ...
+    def is_classed(self) -> bool: ...
+    def is_deep_derivable(self) -> bool: ...
+    def is_derivable(self) -> bool: ...
+    def is_instantiatable(self) -> bool: ...
+    def is_interface(self) -> bool: ...
+    def is_value_abstract(self) -> bool: ...
+    def is_value_type(self) -> bool: ...
+    def has_value_table(self) -> bool: ...
+
+TYPE_INVALID: GType
+TYPE_NONE: GType
+TYPE_INTERFACE: GType
+TYPE_CHAR: GType
+TYPE_UCHAR: GType
+TYPE_BOOLEAN: GType
+TYPE_INT: GType
+TYPE_UINT: GType
+TYPE_LONG: GType
+TYPE_ULONG: GType
+TYPE_INT64: GType
+TYPE_UINT64: GType
+TYPE_ENUM: GType
+TYPE_FLAGS: GType
+TYPE_FLOAT: GType
+TYPE_DOUBLE: GType
+TYPE_STRING: GType
+TYPE_POINTER: GType
+TYPE_BOXED: GType
+TYPE_PARAM: GType
+TYPE_OBJECT: GType
+TYPE_PYOBJECT: GType
+TYPE_GTYPE: GType
+TYPE_STRV: GType
+TYPE_VARIANT: GType
+
+# defined in python by PyGObject
+class Property(typing.Generic[_T]):
+    """A property of a GObject subclass, declared in its body like
+    ``count = GObject.Property(type=int, default=0)``, or decorating its getter
+    """
+    name: str
+    type: typing.Any
+    default: typing.Optional[_T]
+    nick: str
+    blurb: str
+    flags: ParamFlags
+    minimum: typing.Any
+    maximum: typing.Any
+    @typing.overload
+    def __init__(
+        self: Property[_T],
+        getter: typing.Callable[[typing.Any], _T],
+        setter: typing.Optional[typing.Callable[[typing.Any, _T], None]] = None,
+        type: typing.Optional[builtins.type[_T]] = None,
+        default: typing.Optional[_T] = None,
+        nick: str = "",
+        blurb: str = "",
+        flags: ParamFlags = ...,
+        minimum: typing.Any = None,
+        maximum: typing.Any = None,
+    ) -> None: ...
+    @typing.overload
+    def __init__(
+        self: Property[_T],
+        getter: None = None,
+        setter: None = None,
+        *,
+        type: builtins.type[_T],
+        default: typing.Optional[_T] = None,
+        nick: str = "",
+        blurb: str = "",
+        flags: ParamFlags = ...,
+        minimum: typing.Any = None,
+        maximum: typing.Any = None,
+    ) -> None: ...
+    @typing.overload
+    def __init__(
+        self: Property[_T],
+        getter: None = None,
+        setter: None = None,
+        *,
+        default: _T,
+        nick: str = "",
+        blurb: str = "",
+        flags: ParamFlags = ...,
+        minimum: typing.Any = None,
+        maximum: typing.Any = None,
+    ) -> None: ...
+    @typing.overload
+    def __get__(self, instance: None, klass: typing.Any = None) -> Property[_T]: ...
+    @typing.overload
+    def __get__(self, instance: Object, klass: typing.Any = None) -> _T: ...
+    def __set__(self, instance: Object, value: _T) -> None: ...
+    def __call__(self, fget: typing.Callable[[typing.Any], _T]) -> Property[_T]: ...
+    def getter(self, fget: typing.Callable[[typing.Any], _T]) -> Property[_T]: ...
+    def setter(self, fset: typing.Callable[[typing.Any, _T], None]) -> Property[_T]: ...
+    def get_pspec_args(self) -> tuple[typing.Any, ...]: ...
+
+# defined in python by PyGObject
+class Signal(str):
+    """A signal of a GObject subclass, declared in its body like
+    ``changed = GObject.Signal(arg_types=(int,))``, or decorating its class
+    closure
+    """
+    class BoundSignal(str):
+        def __call__(self, *args: typing.Any, **kargs: typing.Any) -> typing.Any: ...
+        def emit(self, *args: typing.Any, **kargs: typing.Any) -> typing.Any: ...
+        def connect(self, callback: typing.Callable[..., typing.Any], *args: typing.Any, **kargs: typing.Any) -> int: ...
+        def connect_detailed(self, callback: typing.Callable[..., typing.Any], detail: str, *args: typing.Any, **kargs: typing.Any) -> int: ...
+        def disconnect(self, handler_id: int) -> None: ...
+    name: str
+    func: typing.Optional[typing.Callable[..., typing.Any]]
+    flags: SignalFlags
+    return_type: typing.Any
+    arg_types: typing.Sequence[typing.Any]
+    accumulator: typing.Optional[typing.Callable[..., typing.Any]]
+    accu_data: typing.Any
+    def __init__(
+        self,
+        name: typing.Union[str, typing.Callable[..., typing.Any]] = "",
+        func: typing.Optional[typing.Callable[..., typing.Any]] = None,
+        flags: SignalFlags = ...,
+        return_type: typing.Any = None,
+        arg_types: typing.Optional[typing.Sequence[typing.Any]] = None,
+        doc: str = "",
+        accumulator: typing.Optional[typing.Callable[..., typing.Any]] = None,
+        accu_data: typing.Any = None,
+    ) -> None: ...
+    @typing.overload
+    def __get__(self, instance: None, owner: typing.Any = None) -> Signal: ...
+    @typing.overload
+    def __get__(self, instance: Object, owner: typing.Any = None) -> Signal.BoundSignal: ...
+    @typing.overload
+    def __call__(self, obj: typing.Callable[..., typing.Any]) -> Signal: ...
+    @typing.overload
+    def __call__(self, obj: Object, *args: typing.Any, **kargs: typing.Any) -> typing.Any: ...
+    def copy(self, newName: typing.Optional[str] = None) -> Signal: ...
+    def get_signal_args(self) -> tuple[typing.Any, ...]: ...
+
+# defined in python by PyGObject
+class SignalOverride(Signal):
+    """Overrides the class closure of a signal of a parent class"""
+
+class Object:
+    __gtype__: typing.ClassVar[GType]
+    __gtype_name__: typing.ClassVar[str]
+    __gsignals__: typing.ClassVar[dict[str, typing.Any]]
+    __gproperties__: typing.ClassVar[dict[str, tuple[typing.Any, ...]]]
+    props: typing.Any
+    def __init__(self, **properties: typing.Any) -> None: ...
+    def get_property(self, property_name: str) -> typing.Any: ...
+    def set_property(self, property_name: str, value: typing.Any) -> None: ...
+    def get_properties(self, *prop_names: str) -> tuple[typing.Any, ...]: ...
+    def set_properties(self, **kwargs: typing.Any) -> None: ...
+    def bind_property(
+        self,
+        source_property: str,
+        target: Object,
+        target_property: str,
+        flags: BindingFlags = ...,
+        transform_to: typing.Optional[typing.Callable[[Binding, typing.Any], typing.Any]] = None,
+        transform_from: typing.Optional[typing.Callable[[Binding, typing.Any], typing.Any]] = None,
+        user_data: typing.Any = None,
+    ) -> Binding: ...
+    def freeze_notify(self) -> typing.ContextManager[None]:
+        """Freezes the notify signals until the ``with`` block ends"""
+    def connect(self, detailed_signal: str, handler: typing.Callable[..., typing.Any], *args: typing.Any) -> int: ...
+    def connect_after(self, detailed_signal: str, handler: typing.Callable[..., typing.Any], *args: typing.Any) -> int: ...
+    def connect_object(self, detailed_signal: str, handler: typing.Callable[..., typing.Any], object: Object, *args: typing.Any) -> int: ...
+    def connect_object_after(self, detailed_signal: str, handler: typing.Callable[..., typing.Any], object: Object, *args: typing.Any) -> int: ...
+    def connect_data(self, detailed_signal: str, handler: typing.Callable[..., typing.Any], *data: typing.Any, connect_flags: ConnectFlags = ...) -> int: ...
+    def disconnect(self, id: int) -> None: ...
+    def disconnect_by_func(self, func: typing.Callable[..., typing.Any]) -> None: ...
+    def handler_block(self, handler_id: int) -> typing.ContextManager[None]:
+        """Blocks the handler until the ``with`` block ends"""
+    def handler_unblock(self, handler_id: int) -> None: ...
+    def handler_disconnect(self, handler_id: int) -> None: ...
+    def handler_is_connected(self, handler_id: int) -> bool: ...
+    def handler_block_by_func(self, func: typing.Callable[..., typing.Any]) -> int: ...
+    def handler_unblock_by_func(self, func: typing.Callable[..., typing.Any]) -> int: ...
+    def emit(self, signal_name: str, *args: typing.Any) -> typing.Any: ...
+    def stop_emission_by_name(self, detailed_signal: str) -> None: ...
+    def chain(self, *args: typing.Any) -> typing.Any: ...
+    def weak_ref(self, callback: typing.Optional[typing.Callable[..., typing.Any]] = None, *args: typing.Any) -> typing.Any: ...
+
+class Value:
+    def __init__(self, value_type: typing.Optional[GType] = None, py_value: typing.Any = None) -> None: ...
+    def get_value(self) -> typing.Any: ...
+    def set_value(self, py_value: typing.Any) -> None: ...
+
+def signal_handler_block(obj: Object, handler_id: int) -> typing.ContextManager[None]:
+    """Blocks the handler until the ``with`` block ends"""
+def signal_handler_unblock(instance: Object, handler_id: int) -> None: ...
+def signal_handler_disconnect(instance: Object, handler_id: int) -> None: ...
+def signal_handler_is_connected(instance: Object, handler_id: int) -> bool: ...
+def signal_stop_emission_by_name(instance: Object, detailed_signal: str) -> None: ...
+def signal_new(signal_name: str, itype: typing.Any, signal_flags: SignalFlags, return_type: typing.Any, param_types: typing.Sequence[typing.Any]) -> int: ...
+def signal_list_ids(type_: typing.Any) -> tuple[int, ...]: ...
+def signal_list_names(type_: typing.Any) -> tuple[str, ...]: ...
+def signal_lookup(name: str, type_: typing.Any) -> int: ...
+def signal_name(signal_id: int) -> typing.Optional[str]: ...
+def signal_query(id_or_name: typing.Union[int, str], type_: typing.Any = None) -> typing.Optional[SignalQuery]: ...
+def add_emission_hook(type: typing.Any, name: str, callback: typing.Callable[..., bool], *user_data: typing.Any) -> int: ...
+def list_properties(type_: typing.Any) -> list[ParamSpec]: ...
+def new(gtype: typing.Any, **kwargs: typing.Any) -> Object: ...
+def type_register(class_: type[_T]) -> GType: ...
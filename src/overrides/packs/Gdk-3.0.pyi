@@ -0,0 +1,22 @@
+# Merged into the generated Gdk 3 stubs, after Gdk.pyi
+import typing
+import cairo
+
+class RGBA:
+    def to_color(self) -> Color: ...
+    @staticmethod
+    def from_color(color: Color) -> RGBA: ...
+
+class Color:
+    MAX_VALUE: typing.ClassVar[int]
+    red_float: float
+    green_float: float
+    blue_float: float
+    def __init__(self, red: int, green: int, blue: int) -> None: ...
+    def to_floats(self) -> tuple[float, float, float]: ...
+    @staticmethod
+    def from_floats(red: float, green: float, blue: float) -> Color: ...
+
+class Window:
+    def __init__(self, parent: typing.Optional[Window], attributes: WindowAttr, attributes_mask: WindowAttributesType) -> None: ...
+    def cairo_create(self) -> cairo.Context: ...
@@ -0,0 +1,11 @@
+# Merged into the generated Gdk stubs, based on gi/overrides/Gdk.py in
+# PyGObject. What's only in GDK 3 is in Gdk-3.0.pyi
+import typing
+
+class RGBA:
+    red: float
+    green: float
+    blue: float
+    alpha: float
+    def __init__(self, red: float = 1.0, green: float = 1.0, blue: float = 1.0, alpha: float = 1.0) -> None: ...
+    def __iter__(self) -> typing.Iterator[float]: ...
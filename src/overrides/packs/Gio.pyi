@@ -0,0 +1,80 @@
+# Merged into the generated Gio stubs, based on gi/overrides/Gio.py in
+# PyGObject. T is the type variable of the generic list models
+import typing
+
+_ActionEntry = typing.Union[
+    tuple[str],
+    tuple[str, typing.Optional[typing.Callable[..., typing.Any]]],
+    tuple[str, typing.Optional[typing.Callable[..., typing.Any]], typing.Optional[str]],
+    tuple[str, typing.Optional[typing.Callable[..., typing.Any]], typing.Optional[str], typing.Optional[str]],
+    tuple[str, typing.Optional[typing.Callable[..., typing.Any]], typing.Optional[str], typing.Optional[str], typing.Optional[typing.Callable[..., typing.Any]]],
+]
+
+class Settings:
+    def __init__(self, schema_id: typing.Optional[str] = None, path: typing.Optional[str] = None, **properties: typing.Any) -> None: ...
+    def __contains__(self, key: str) -> bool: ...
+    def __len__(self) -> int: ...
+    def __bool__(self) -> bool: ...
+    def __getitem__(self, key: str) -> typing.Any:
+        """The value of the key, unpacked from its variant"""
+    def __setitem__(self, key: str, value: typing.Any) -> None:
+        """Sets the key, packing the value with the type of the key"""
+    def keys(self) -> list[str]: ...
+
+class ListModel:
+    def get_item(self, position: int) -> typing.Optional[T]: ...
+    def __len__(self) -> int: ...
+    def __bool__(self) -> bool: ...
+    def __contains__(self, item: object) -> bool: ...
+    def __iter__(self) -> typing.Iterator[T]: ...
+    @typing.overload
+    def __getitem__(self, key: int) -> T: ...
+    @typing.overload
+    def __getitem__(self, key: slice) -> list[T]: ...
+
+class ListStore:
+    def __init__(self, *, item_type: type[T]) -> None: ...
+    def append(self, item: T) -> None: ...
+    def insert(self, position: int, item: T) -> None: ...
+    def insert_sorted(self, item: T, compare_func: typing.Callable[..., int], *user_data: typing.Any) -> int: ...
+    def sort(self, compare_func: typing.Callable[..., int], *user_data: typing.Any) -> None: ...
+    def splice(self, position: int, n_removals: int, additions: typing.Sequence[T]) -> None: ...
+    def find(self, item: T) -> tuple[bool, int]: ...
+    def find_with_equal_func(self, item: T, equal_func: typing.Callable[[T, T], bool]) -> tuple[bool, int]: ...
+    def remove(self, position: int) -> None: ...
+    def remove_all(self) -> None: ...
+    @typing.overload
+    def __setitem__(self, key: int, value: T) -> None: ...
+    @typing.overload
+    def __setitem__(self, key: slice, value: typing.Iterable[T]) -> None: ...
+    def __delitem__(self, key: typing.Union[int, slice]) -> None: ...
+
+class ActionMap:
+    def add_action_entries(self, entries: typing.Iterable[_ActionEntry], user_data: typing.Any = None) -> None:
+        """Adds actions from tuples of a name, an activate callback, a
+        parameter type, a state and a change state callback. Only the name is
+        required
+        """
+
+class MenuItem:
+    def set_attribute(self, attributes: typing.Iterable[tuple[str, str, typing.Any]]) -> None:
+        """Sets the attributes from tuples of a name, a variant format string
+        and a value
+        """
+
+class FileEnumerator:
+    def __iter__(self) -> FileEnumerator: ...
+    def __next__(self) -> FileInfo: ...
+
+# defined in python by PyGObject
+class _DBusProxyMethodCall:
+    """A method of the remote object, called with the variant signature of
+    the arguments followed by the arguments. The keyword arguments are
+    result_handler, error_handler, user_data, flags and timeout
+    """
+    def __init__(self, dbus_proxy: DBusProxy, method_name: str) -> None: ...
+    def __call__(self, *args: typing.Any, **kwargs: typing.Any) -> typing.Any: ...
+
+class DBusProxy:
+    def __getattr__(self, name: str) -> _DBusProxyMethodCall:
+        """The methods of the remote object, like proxy.Method("(s)", "x")"""
@@ -0,0 +1,29 @@
+# Merged into the generated Gtk 3 stubs, after Gtk.pyi
+import typing
+from gi.repository import GObject
+
+class Widget:
+    def translate_coordinates(self, dest_widget: Widget, src_x: int, src_y: int) -> typing.Optional[tuple[int, int]]: ...
+    def freeze_child_notify(self) -> typing.ContextManager[None]:
+        """Freezes the child notify signals until the with block ends"""
+
+class Container:
+    def __len__(self) -> int: ...
+    def __contains__(self, child: Widget) -> bool: ...
+    def __iter__(self) -> typing.Iterator[Widget]: ...
+    def __bool__(self) -> bool: ...
+    def child_get_property(self, child: Widget, property_name: str, value: typing.Optional[GObject.Value] = None) -> typing.Any: ...
+    def child_get(self, child: Widget, *prop_names: str) -> tuple[typing.Any, ...]: ...
+    def child_set(self, child: Widget, **kwargs: typing.Any) -> None: ...
+    def get_focus_chain(self) -> typing.Optional[list[Widget]]: ...
+
+class Builder:
+    def connect_signals(self, obj_or_map: typing.Any) -> None:
+        """Connects the handlers named in the UI definition to the methods of
+        an object, or the values of a mapping
+        """
+    def add_from_string(self, buffer: str) -> int: ...
+    def add_objects_from_string(self, buffer: str, object_ids: list[str]) -> int: ...
+
+class Menu:
+    def popup(self, parent_menu_shell: typing.Optional[Widget], parent_menu_item: typing.Optional[Widget], func: typing.Optional[typing.Callable[..., typing.Any]], data: typing.Any, button: int, activate_time: int) -> None: ...
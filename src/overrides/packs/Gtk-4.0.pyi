@@ -0,0 +1,13 @@
+# Merged into the generated Gtk 4 stubs, after Gtk.pyi
+import typing
+
+class Widget:
+    def translate_coordinates(self, dest_widget: Widget, src_x: float, src_y: float) -> typing.Optional[tuple[float, float]]: ...
+
+class Builder:
+    def __init__(self, scope_object_or_map: typing.Any = None) -> None:
+        """Handlers named in the UI definition are looked up in the methods of
+        the object, or the values of the mapping
+        """
+    def add_from_string(self, buffer: str) -> bool: ...
+    def add_objects_from_string(self, buffer: str, object_ids: list[str]) -> bool: ...
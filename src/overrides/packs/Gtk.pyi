@@ -0,0 +1,133 @@
+# Merged into the generated Gtk stubs, based on gi/overrides/Gtk.py and
+# gi/_gtktemplate.py in PyGObject. What changed between GTK 3 and 4 is in
+# Gtk-3.0.pyi and Gtk-4.0.pyi
+import typing
+from gi.repository import GLib
+
+_F = typing.TypeVar("_F", bound=typing.Callable[..., typing.Any])
+_C = typing.TypeVar("_C", bound=type)
+_TreeKey = typing.Union[TreeIter, TreePath, int, str, tuple[int, ...]]
+_Row = typing.Optional[typing.Sequence[typing.Any]]
+
+# defined in python by PyGObject
+class Template:
+    """Builds the widgets of the decorated class from a template"""
+    def __init__(self, *, string: typing.Optional[typing.Union[str, bytes, GLib.Bytes]] = None, filename: typing.Optional[str] = None, resource_path: typing.Optional[str] = None) -> None: ...
+    def __call__(self, cls: _C) -> _C: ...
+    @classmethod
+    def from_file(cls, filename: str) -> Template: ...
+    @classmethod
+    def from_string(cls, string: typing.Union[str, bytes, GLib.Bytes]) -> Template: ...
+    @classmethod
+    def from_resource(cls, resource_path: str) -> Template: ...
+    @staticmethod
+    def Child(name: typing.Optional[str] = None, *, internal: bool = False) -> typing.Any:
+        """An object of the template, assigned to an attribute of the class"""
+    @staticmethod
+    def Callback(name: typing.Optional[str] = None) -> typing.Callable[[_F], _F]:
+        """Makes the method a handler of a signal of the template"""
+
+# defined in python by PyGObject
+class TreeModelRow:
+    """A row of a tree model, indexed by column"""
+    model: TreeModel
+    iter: TreeIter
+    path: TreePath
+    next: typing.Optional[TreeModelRow]
+    previous: typing.Optional[TreeModelRow]
+    parent: typing.Optional[TreeModelRow]
+    def __init__(self, model: TreeModel, iter_or_path: typing.Union[TreeIter, TreePath]) -> None: ...
+    def __getitem__(self, key: typing.Union[int, slice]) -> typing.Any: ...
+    def __setitem__(self, key: typing.Union[int, slice], value: typing.Any) -> None: ...
+    def __delitem__(self, key: typing.Union[int, slice]) -> None: ...
+    def __iter__(self) -> typing.Iterator[typing.Any]: ...
+    def __len__(self) -> int: ...
+    def iterchildren(self) -> TreeModelRowIter: ...
+
+# defined in python by PyGObject
+class TreeModelRowIter:
+    def __iter__(self) -> TreeModelRowIter: ...
+    def __next__(self) -> TreeModelRow: ...
+
+class TreeModel:
+    def __len__(self) -> int: ...
+    def __bool__(self) -> bool: ...
+    def __iter__(self) -> TreeModelRowIter: ...
+    def __getitem__(self, key: _TreeKey) -> TreeModelRow: ...
+    def __setitem__(self, key: _TreeKey, value: typing.Sequence[typing.Any]) -> None: ...
+    def __delitem__(self, key: _TreeKey) -> None: ...
+    def get_iter(self, path: typing.Union[TreePath, int, str, tuple[int, ...]]) -> TreeIter: ...
+    def get_iter_first(self) -> typing.Optional[TreeIter]: ...
+    def iter_next(self, iter: TreeIter) -> typing.Optional[TreeIter]: ...
+    def iter_previous(self, iter: TreeIter) -> typing.Optional[TreeIter]: ...
+    def iter_children(self, parent: typing.Optional[TreeIter]) -> typing.Optional[TreeIter]: ...
+    def iter_nth_child(self, parent: typing.Optional[TreeIter], n: int) -> typing.Optional[TreeIter]: ...
+    def iter_parent(self, child: TreeIter) -> typing.Optional[TreeIter]: ...
+    def get(self, treeiter: TreeIter, *columns: int) -> tuple[typing.Any, ...]: ...
+    def get_value(self, iter: TreeIter, column: int) -> typing.Any: ...
+    def set_row(self, treeiter: TreeIter, row: typing.Sequence[typing.Any]) -> None: ...
+    def filter_new(self, root: typing.Optional[TreePath] = None) -> TreeModelFilter: ...
+    def foreach(self, func: typing.Callable[..., bool], *user_data: typing.Any) -> None: ...
+
+class TreePath:
+    def __init__(self, path: typing.Union[int, str, typing.Sequence[int]] = 0) -> None: ...
+    def __len__(self) -> int: ...
+    def __iter__(self) -> typing.Iterator[int]: ...
+    def __getitem__(self, index: int) -> int: ...
+    def __eq__(self, other: object) -> bool: ...
+    def __lt__(self, other: TreePath) -> bool: ...
+    def __le__(self, other: TreePath) -> bool: ...
+    def __gt__(self, other: TreePath) -> bool: ...
+    def __ge__(self, other: TreePath) -> bool: ...
+
+class ListStore:
+    def __init__(self, *column_types: typing.Any) -> None: ...
+    def append(self, row: _Row = None) -> TreeIter: ...
+    def prepend(self, row: _Row = None) -> TreeIter: ...
+    def insert(self, position: int, row: _Row = None) -> TreeIter: ...
+    def insert_before(self, sibling: typing.Optional[TreeIter], row: _Row = None) -> TreeIter: ...
+    def insert_after(self, sibling: typing.Optional[TreeIter], row: _Row = None) -> TreeIter: ...
+    def set_value(self, treeiter: TreeIter, column: int, value: typing.Any) -> None: ...
+    def set(self, treeiter: TreeIter, *args: typing.Any) -> None: ...
+
+class TreeStore:
+    def __init__(self, *column_types: typing.Any) -> None: ...
+    def append(self, parent: typing.Optional[TreeIter], row: _Row = None) -> TreeIter: ...
+    def prepend(self, parent: typing.Optional[TreeIter], row: _Row = None) -> TreeIter: ...
+    def insert(self, parent: typing.Optional[TreeIter], position: int, row: _Row = None) -> TreeIter: ...
+    def insert_before(self, parent: typing.Optional[TreeIter], sibling: typing.Optional[TreeIter], row: _Row = None) -> TreeIter: ...
+    def insert_after(self, parent: typing.Optional[TreeIter], sibling: typing.Optional[TreeIter], row: _Row = None) -> TreeIter: ...
+    def set_value(self, treeiter: TreeIter, column: int, value: typing.Any) -> None: ...
+    def set(self, treeiter: TreeIter, *args: typing.Any) -> None: ...
+
+class TreeViewColumn:
+    def __init__(self, title: str = "", cell_renderer: typing.Optional[CellRenderer] = None, **attributes: int) -> None: ...
+    def set_attributes(self, cell_renderer: CellRenderer, **attributes: int) -> None: ...
+
+class TreeView:
+    def insert_column_with_attributes(self, position: int, title: str, cell: CellRenderer, **kwargs: int) -> int: ...
+    def get_path_at_pos(self, x: int, y: int) -> typing.Optional[tuple[TreePath, TreeViewColumn, int, int]]: ...
+
+class TreeSelection:
+    def get_selected(self) -> tuple[TreeModel, typing.Optional[TreeIter]]: ...
+    def get_selected_rows(self) -> tuple[TreeModel, list[TreePath]]: ...
+
+class Adjustment:
+    def __init__(self, value: float = 0, lower: float = 0, upper: float = 0, step_increment: float = 0, page_increment: float = 0, page_size: float = 0) -> None: ...
+
+class Dialog:
+    def add_buttons(self, *args: typing.Any) -> None:
+        """Adds buttons from pairs of labels and responses"""
+
+class TextBuffer:
+    def create_tag(self, tag_name: typing.Optional[str] = None, **properties: typing.Any) -> TextTag: ...
+    def set_text(self, text: str, length: int = -1) -> None: ...
+    def insert(self, iter: TextIter, text: str, length: int = -1) -> None: ...
+    def insert_at_cursor(self, text: str, length: int = -1) -> None: ...
+    def insert_with_tags(self, iter: TextIter, text: str, *tags: TextTag) -> None: ...
+    def insert_with_tags_by_name(self, iter: TextIter, text: str, *tags: str) -> None: ...
+    def get_selection_bounds(self) -> typing.Union[tuple[TextIter, TextIter], tuple[()]]: ...
+
+class TextIter:
+    def forward_search(self, str: str, flags: TextSearchFlags, limit: typing.Optional[TextIter] = None) -> typing.Optional[tuple[TextIter, TextIter]]: ...
+    def backward_search(self, str: str, flags: TextSearchFlags, limit: typing.Optional[TextIter] = None) -> typing.Optional[tuple[TextIter, TextIter]]: ...
@@ -0,0 +1,588 @@
+//! Reads the python overrides of PyGObject, like `gi/overrides/Gtk.py`, to
+//! find the classes and functions it replaces or adds. Only the signatures
+//! are taken, the types come from the generated declarations when the
+//! parameters keep their names. The blocks under `if GTK3:` and the like are
+//! picked by the version of the namespace
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use super::{
+    python::{parse_def, split_statements, Statement, StatementKind},
+    Transofrmer,
+};
+use crate::{
+    declarations::{Class, Function, FunctionKind, Namespace, Param, Type, Version},
+    passes::Pass,
+    typ,
+};
+
+lazy_static! {
+    static ref VERSION_CHECK: Regex =
+        Regex::new(r"^(\w+)\._version\s*(==|!=|not\s+in|in)\s*(.+)$").unwrap();
+    static ref QUOTED: Regex = Regex::new(r#"["']([^"']*)["']"#).unwrap();
+}
+
+/// The `gi/overrides` directory of PyGObject, found with `--pygobject` or
+/// passed with `--pygobject-overrides`
+pub struct PyGObjectOverrides {
+    dir: PathBuf,
+}
+
+/// Evaluates the conditions of the `if` blocks, as far as the version of the
+/// namespace tells
+struct Conditions<'a> {
+    namespace: &'a str,
+    version: Option<&'a Version>,
+    /// variables like `GTK3 = Gtk._version == '3.0'`
+    flags: HashMap<String, bool>,
+}
+
+/// Splits the condition at the first `op` outside of parentheses and strings
+fn split_top_level<'a>(condition: &'a str, op: &str) -> Option<(&'a str, &'a str)> {
+    let mut depth = 0;
+    let mut quote = None;
+
+    for (i, c) in condition.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '(' | '[' | '{') => depth += 1,
+            (None, ')' | ']' | '}') => depth -= 1,
+            (None, _) if depth == 0 && condition[i..].starts_with(op) => {
+                return Some((&condition[..i], &condition[i + op.len()..]));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// The condition inside the parentheses, if they wrap all of it
+fn strip_parens(condition: &str) -> Option<&str> {
+    let inner = condition.strip_prefix('(')?.strip_suffix(')')?;
+    // like `(a) or (b)`, which isn't wrapped as a whole
+    let mut depth = 0;
+    for c in inner.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' if depth == 0 => return None,
+            ')' => depth -= 1,
+            _ => {}
+        }
+    }
+    Some(inner)
+}
+
+/// Where an `if` chain is, while reading its `elif` and `else` blocks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Chain {
+    /// the last statement wasn't part of an `if` chain
+    Outside,
+    Taken,
+    NotTaken,
+    /// a condition couldn't be evaluated, so what follows may run too
+    Unknown,
+}
+
+impl<'a> Conditions<'a> {
+    /// `None` when the condition depends on something else, like `hasattr`
+    fn eval(&self, condition: &str) -> Option<bool> {
+        let condition = condition.trim();
+
+        if let Some((a, b)) = split_top_level(condition, " or ") {
+            return match (self.eval(a), self.eval(b)) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            };
+        }
+        if let Some((a, b)) = split_top_level(condition, " and ") {
+            return match (self.eval(a), self.eval(b)) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            };
+        }
+        if let Some(condition) = condition.strip_prefix("not ") {
+            return self.eval(condition).map(|c| !c);
+        }
+        if let Some(condition) = strip_parens(condition) {
+            return self.eval(condition);
+        }
+
+        if let Some(caps) = VERSION_CHECK.captures(condition) {
+            if &caps[1] != self.namespace {
+                return None;
+            }
+            let version = self.version?;
+            let matches = QUOTED
+                .captures_iter(&caps[3])
+                .filter_map(|v| v[1].parse::<Version>().ok())
+                .any(|v| v == *version);
+
+            return match &caps[2] {
+                "==" | "in" => Some(matches),
+                _ => Some(!matches),
+            };
+        }
+
+        self.flags.get(condition).copied()
+    }
+
+    /// Keeps the statements that run with this version, taking them out of
+    /// the `if` and `try` blocks
+    fn active(&mut self, statements: Vec<Statement>) -> Vec<Statement> {
+        let mut active = Vec::new();
+        let mut chain = Chain::Outside;
+
+        for statement in statements {
+            if statement.kind != StatementKind::Other {
+                chain = Chain::Outside;
+                if statement.kind == StatementKind::Var {
+                    let (name, value) = statement.source.split_once('=').unwrap_or_default();
+                    if let Some(flag) = self.eval(value) {
+                        self.flags.insert(name.trim().to_string(), flag);
+                    }
+                }
+                active.push(statement);
+                continue;
+            }
+
+            let (header, body) = statement.split_block();
+            let (keyword, condition) = header.split_once(' ').unwrap_or((header, ""));
+
+            // what's under an unknown condition likely exists
+            let (run, next) = match (keyword, chain) {
+                ("if", _) | ("elif", Chain::NotTaken) => match self.eval(condition) {
+                    Some(true) => (true, Chain::Taken),
+                    Some(false) => (false, Chain::NotTaken),
+                    None => (true, Chain::Unknown),
+                },
+                ("elif", Chain::Unknown) => (self.eval(condition) != Some(false), Chain::Unknown),
+                ("elif", _) => (false, chain),
+                ("else", Chain::NotTaken | Chain::Unknown) => (true, Chain::Outside),
+                ("else", _) => (false, Chain::Outside),
+                ("try", _) => (true, Chain::Outside),
+                ("except" | "finally" | "for" | "while" | "with", _) => (false, Chain::Outside),
+                // like `__all__.append('Widget')`
+                _ => {
+                    chain = Chain::Outside;
+                    active.push(statement);
+                    continue;
+                }
+            };
+            chain = next;
+
+            if run {
+                active.extend(self.active(body));
+            }
+        }
+
+        active
+    }
+}
+
+/// Names listed in `__all__`
+fn exports(statements: &[Statement]) -> HashSet<String> {
+    statements
+        .iter()
+        .filter(|s| s.source.starts_with("__all__"))
+        .flat_map(|s| QUOTED.captures_iter(&s.source).map(|c| c[1].to_string()))
+        .collect()
+}
+
+/// Whether the name is part of the API, dunder methods included. The ones
+/// that every object has, or that python 2 used, are left out
+fn is_public(name: &str) -> bool {
+    match name {
+        "__new__" | "__del__" | "__repr__" | "__str__" | "__nonzero__" => false,
+        name => !name.starts_with('_') || (name.starts_with("__") && name.ends_with("__")),
+    }
+}
+
+/// Parses the signature of a `def` statement, without the body. `None` for
+/// properties and the decorators that aren't understood
+fn parse_signature(statement: &Statement) -> Option<Function> {
+    let mut source = String::new();
+    for decorator in statement.decorators() {
+        match decorator {
+            "staticmethod" | "classmethod" => source += &format!("@{} ", decorator),
+            "property" => return None,
+            // like `@override(Gtk.main)`, which doesn't change the signature
+            _ => {}
+        }
+    }
+    let (header, _) = statement.split_block();
+    source += header.trim_start_matches("async ");
+
+    parse_def(&source).ok()
+}
+
+/// Whether the parameters are just passed to the overridden function, like
+/// `def __init__(self, *args, **kwargs)`
+fn passes_through(func: &Function) -> bool {
+    func.parameters.iter().all(|p| match p {
+        Param::Variadic { typ, .. } | Param::Keywords { typ, .. } => matches!(typ, Type::Any),
+        Param::Instance => true,
+        _ => false,
+    })
+}
+
+/// Takes the types and the docs of the parameters from the generated
+/// function, for the ones with the same name
+fn merge_signature(python: Function, generated: &Function) -> Function {
+    if passes_through(&python) {
+        return generated.clone();
+    }
+
+    let find = |name: &str| {
+        generated.parameters.iter().find(|p| match p {
+            Param::Named { name: n, .. }
+            | Param::Variadic { name: n, .. }
+            | Param::Keywords { name: n, .. } => n == name,
+            _ => false,
+        })
+    };
+
+    let mut func = generated.clone();
+    func.parameters = python
+        .parameters
+        .into_iter()
+        .map(|param| {
+            let old = match &param {
+                Param::Named { name, .. }
+                | Param::Variadic { name, .. }
+                | Param::Keywords { name, .. } => find(name).cloned(),
+                _ => None,
+            };
+            match (param, old) {
+                (Param::Named { .. }, Some(old @ Param::Named { .. }))
+                | (Param::Variadic { .. }, Some(old @ Param::Variadic { .. }))
+                | (Param::Keywords { .. }, Some(old @ Param::Keywords { .. })) => old,
+                (param, _) => param,
+            }
+        })
+        .collect();
+    // the callback parameters may be gone
+    func.finish = None;
+
+    func
+}
+
+/// Merges the methods of a class of the overrides into the generated one
+fn merge_class(mut class: Class, members: Vec<Statement>) -> Class {
+    for member in members {
+        if member.kind != StatementKind::Def || !is_public(&member.name) {
+            continue;
+        }
+        let mut python = match parse_signature(&member) {
+            Some(python) => python,
+            None => continue,
+        };
+        if python.kind == FunctionKind::Static {
+            python.kind = FunctionKind::StaticMethod;
+        }
+
+        if python.name == "__init__" {
+            class.constructor = merge_signature(python, &class.constructor);
+        } else if class.find_method(&member.name).is_some() {
+            class.transform_method(&member.name, |m| *m = merge_signature(python, m));
+        } else {
+            class.methods.insert(python);
+        }
+    }
+
+    class
+}
+
+/// A class that only exists in the overrides. `None` if a base can't be
+/// told apart, like a python exception
+fn new_class(
+    ns: &Namespace,
+    name: &str,
+    bases: Vec<String>,
+    members: Vec<Statement>,
+) -> Option<Class> {
+    let bases = bases
+        .iter()
+        .filter(|base| *base != "object")
+        .map(|base| match base.split_once('.') {
+            Some((module, class)) if module == ns.name && ns.classes.contains(class) => {
+                Some(Type::LocalClass(class.into()))
+            }
+            Some((module, class)) if ns.imports.contains(module) => Some(Type::ExternalClass {
+                module: module.into(),
+                name: class.into(),
+            }),
+            None if ns.classes.contains(base.as_str()) => Some(Type::LocalClass(base.clone())),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+
+    let class = Class {
+        name: name.into(),
+        bases,
+        fields: Vec::new(),
+        methods: Default::default(),
+        constructor: Function::build("__init__")
+            .kind(FunctionKind::Method)
+            .returns(typ!(None)),
+        doc: None,
+        deprecated: None,
+        since: None,
+        c_name: None,
+        source: None,
+        is_abstract: false,
+        is_final: false,
+        raw_doc: false,
+        snippets: Vec::new(),
+    };
+
+    Some(merge_class(class, members))
+}
+
+/// Merges the signatures of the python overrides of a namespace
+fn merge_overrides(ns: &mut Namespace, source: &str) -> Result<(), String> {
+    let name = ns.name.clone();
+    let version = ns.version.clone();
+    let mut conditions = Conditions {
+        namespace: &name,
+        version: version.as_ref(),
+        flags: HashMap::new(),
+    };
+    let statements = conditions.active(split_statements(source));
+    let exports = exports(&statements);
+
+    for statement in statements {
+        if !exports.contains(&statement.name) || !is_public(&statement.name) {
+            continue;
+        }
+
+        match statement.kind {
+            StatementKind::Class => {
+                let (bases, body) = statement.split_class();
+                let members = conditions.active(body);
+
+                let bases = bases.unwrap_or_default();
+                // like `class Widget(Gtk.Widget)`
+                let overrides = bases
+                    .iter()
+                    .any(|b| b.rsplit('.').next() == Some(&statement.name));
+
+                if ns.classes.contains(statement.name.as_str()) {
+                    Transofrmer(ns)
+                        .transform_class(&statement.name, |c| Ok(merge_class(c, members)))?;
+                } else if !overrides {
+                    ns.classes
+                        .extend(new_class(ns, &statement.name, bases, members));
+                }
+                // else the class is missing from this version of the library
+            }
+            StatementKind::Def => {
+                let python = match parse_signature(&statement) {
+                    Some(python) => python.kind(FunctionKind::Static),
+                    None => continue,
+                };
+                match Transofrmer(ns).function(&statement.name) {
+                    Some(func) => *func = merge_signature(python, func),
+                    None => ns.functions.push(python),
+                }
+            }
+            // aliases and wrapped functions can't be told apart
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+impl PyGObjectOverrides {
+    /// Uses the overrides in `dir`, or the ones of the PyGObject installed
+    /// for `python3`
+    pub fn new(dir: Option<PathBuf>) -> Result<Self, String> {
+        let dir = match dir {
+            Some(dir) => dir,
+            None => Self::locate()?,
+        };
+        if !dir.is_dir() {
+            return Err(format!("{}: not a directory", dir.display()));
+        }
+        Ok(Self { dir })
+    }
+
+    /// Finds `gi/overrides`, without importing `gi`
+    fn locate() -> Result<PathBuf, String> {
+        let output = Command::new("python3")
+            .args([
+                "-c",
+                "import importlib.util as u; print(u.find_spec('gi').submodule_search_locations[0])",
+            ])
+            .output()
+            .map_err(|e| format!("couldn't run python3: {}", e))?;
+
+        match String::from_utf8(output.stdout) {
+            Ok(gi) if output.status.success() => Ok(Path::new(gi.trim()).join("overrides")),
+            _ => Err(String::from(
+                "PyGObject isn't installed for python3, \
+                 pass its gi/overrides directory with --pygobject-overrides",
+            )),
+        }
+    }
+
+    pub fn apply(&self, ns: &mut Namespace) -> Result<(), String> {
+        let path = self.dir.join(format!("{}.py", ns.name));
+        if !path.exists() {
+            return Ok(());
+        }
+        let source = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        merge_overrides(ns, &source).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+impl Pass for PyGObjectOverrides {
+    fn namespace(&mut self, ns: &mut Namespace) -> Result<(), String> {
+        self.apply(ns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GTK3: &str = "3.0";
+    const GTK4: &str = "4.0";
+
+    fn conditions(version: &Version) -> Conditions<'_> {
+        Conditions {
+            namespace: "Gtk",
+            version: Some(version),
+            flags: HashMap::new(),
+        }
+    }
+
+    /// Names of the statements that run with the version of Gtk
+    fn active(version: &str, source: &str) -> Vec<String> {
+        let version = version.parse().unwrap();
+        conditions(&version)
+            .active(split_statements(source))
+            .into_iter()
+            .map(|s| s.name)
+            .collect()
+    }
+
+    fn eval(version: &str, condition: &str) -> Option<bool> {
+        let version = version.parse().unwrap();
+        conditions(&version).eval(condition)
+    }
+
+    #[test]
+    fn version_checks() {
+        assert_eq!(eval(GTK3, "Gtk._version == '3.0'"), Some(true));
+        assert_eq!(eval(GTK4, "Gtk._version == '3.0'"), Some(false));
+        assert_eq!(eval(GTK4, "Gtk._version != \"3.0\""), Some(true));
+        assert_eq!(eval(GTK4, "Gtk._version in ('3.0', '4.0')"), Some(true));
+        assert_eq!(eval(GTK4, "Gtk._version not in ('2.0', '3.0')"), Some(true));
+        assert_eq!(
+            eval(GTK3, "Gtk._version not in ('2.0', '3.0')"),
+            Some(false)
+        );
+        // other namespaces and other checks aren't known
+        assert_eq!(eval(GTK4, "Gdk._version == '4.0'"), None);
+        assert_eq!(eval(GTK4, "hasattr(Gtk, 'Widget')"), None);
+    }
+
+    #[test]
+    fn operators() {
+        assert_eq!(eval(GTK4, "not Gtk._version == '3.0'"), Some(true));
+        assert_eq!(eval(GTK4, "not (Gtk._version == '4.0')"), Some(false));
+        assert_eq!(
+            eval(GTK4, "Gtk._version == '3.0' or Gtk._version == '4.0'"),
+            Some(true)
+        );
+        assert_eq!(
+            eval(GTK4, "Gtk._version == '4.0' and hasattr(Gtk, 'x')"),
+            None
+        );
+        assert_eq!(
+            eval(GTK4, "Gtk._version == '3.0' and hasattr(Gtk, 'x')"),
+            Some(false)
+        );
+        assert_eq!(
+            eval(GTK4, "hasattr(Gtk, 'x') or Gtk._version == '4.0'"),
+            Some(true)
+        );
+        // the operators inside strings and calls are left alone
+        assert_eq!(eval(GTK4, "Gtk._version in ('3.0 or 4.0',)"), Some(false));
+    }
+
+    #[test]
+    fn parentheses() {
+        let condition =
+            "(Gtk._version == '3.0' or (Gtk._version == '4.0' and not (Gtk._version == '5.0')))";
+        assert_eq!(eval(GTK3, condition), Some(true));
+        assert_eq!(eval(GTK4, condition), Some(true));
+        assert_eq!(eval("5.0", condition), Some(false));
+        assert_eq!(
+            eval(GTK4, "(Gtk._version == '3.0') or (Gtk._version == '4.0')"),
+            Some(true)
+        );
+        assert_eq!(strip_parens("(a) or (b)"), None);
+        assert_eq!(strip_parens("((a) or (b))"), Some("(a) or (b)"));
+    }
+
+    #[test]
+    fn flags() {
+        let source = "GTK3 = Gtk._version == '3.0'\n\
+                      GTK4 = Gtk._version in ('4.0',)\n\
+                      if GTK3:\n    def three(): pass\n\
+                      if not GTK3 and GTK4:\n    def four(): pass\n";
+        assert_eq!(active(GTK3, source), ["GTK3", "GTK4", "three"]);
+        assert_eq!(active(GTK4, source), ["GTK3", "GTK4", "four"]);
+    }
+
+    #[test]
+    fn chains() {
+        let source = "if Gtk._version == '3.0':\n    def a(): pass\n\
+                      elif Gtk._version == '4.0':\n    def b(): pass\n\
+                      else:\n    def c(): pass\n";
+        assert_eq!(active(GTK3, source), ["a"]);
+        assert_eq!(active(GTK4, source), ["b"]);
+        assert_eq!(active("5.0", source), ["c"]);
+    }
+
+    #[test]
+    fn unknown_conditions() {
+        // the branches after an unknown condition may run, unless they're
+        // known not to
+        let source = "if hasattr(Gtk, 'x'):\n    def a(): pass\n\
+                      elif Gtk._version == '3.0':\n    def b(): pass\n\
+                      elif Gtk._version == '4.0':\n    def c(): pass\n\
+                      else:\n    def d(): pass\n";
+        assert_eq!(active(GTK4, source), ["a", "c", "d"]);
+        assert_eq!(active(GTK3, source), ["a", "b", "d"]);
+
+        let source = "if Gtk._version == '4.0':\n    def a(): pass\n\
+                      elif hasattr(Gtk, 'x'):\n    def b(): pass\n\
+                      else:\n    def c(): pass\n";
+        assert_eq!(active(GTK4, source), ["a"]);
+        assert_eq!(active(GTK3, source), ["b", "c"]);
+    }
+
+    #[test]
+    fn blocks() {
+        let source = "try:\n    def a(): pass\n\
+                      except ImportError:\n    def b(): pass\n\
+                      __all__.append('a')\n\
+                      def c(): pass\n";
+        assert_eq!(active(GTK4, source), ["a", "", "c"]);
+    }
+}
@@ -0,0 +1,510 @@
+//! Parses the python declarations written by hand in the override files, like
+//! `def run(self, argv: list[str]) -> int` or `name: str`, and splits the
+//! extra stubs into statements
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+use crate::declarations::{Function, FunctionKind, Param, Type, Var};
+
+lazy_static! {
+    static ref DEF: Regex =
+        Regex::new(r"(?s)^def\s+(\w+)\s*\((.*)\)\s*(?:->\s*(.+?))?\s*(?::\s*(?:\.\.\.)?)?$")
+            .unwrap();
+    static ref FIELD: Regex = Regex::new(r"^(\w+)\s*:\s*(.+)$").unwrap();
+    static ref STATEMENT: Regex =
+        Regex::new(r"^(?:(?:import|from)\s|class\s+(\w+)|(?:async\s+)?def\s+(\w+)|(\w+)\s*[:=])")
+            .unwrap();
+}
+
+/// What a statement declares
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatementKind {
+    Import,
+    Class,
+    Def,
+    Var,
+    Other,
+}
+
+/// A statement of a python source, with the decorators and comments above it
+#[derive(Debug)]
+pub struct Statement {
+    pub kind: StatementKind,
+    /// name of the declaration, empty for imports and other statements
+    pub name: String,
+    pub source: String,
+}
+
+/// Keeps track of the brackets and strings that span multiple lines
+#[derive(Default)]
+struct Scanner {
+    depth: i32,
+    string: Option<&'static str>,
+}
+
+impl Scanner {
+    fn continues(&self) -> bool {
+        self.depth > 0 || self.string.is_some()
+    }
+
+    fn scan(&mut self, line: &str) {
+        let line = line.as_bytes();
+        let mut i = 0;
+
+        while i < line.len() {
+            let rest = &line[i..];
+
+            if let Some(quote) = self.string {
+                if rest[0] == b'\\' {
+                    i += 2;
+                } else if rest.starts_with(quote.as_bytes()) {
+                    self.string = None;
+                    i += quote.len();
+                } else {
+                    i += 1;
+                }
+                continue;
+            }
+
+            match rest[0] {
+                b'#' => break,
+                b'(' | b'[' | b'{' => self.depth += 1,
+                b')' | b']' | b'}' => self.depth -= 1,
+                b'"' | b'\'' => {
+                    let quote = ["\"\"\"", "\'\'\'", "\"", "\'"]
+                        .into_iter()
+                        .find(|q| rest.starts_with(q.as_bytes()))
+                        .unwrap();
+                    self.string = Some(quote);
+                    i += quote.len();
+                    continue;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+
+        // only triple quoted strings span lines
+        if matches!(self.string, Some("\"") | Some("\'")) {
+            self.string = None;
+        }
+    }
+}
+
+impl Statement {
+    fn new(source: String) -> Self {
+        let caps = source
+            .lines()
+            .filter(|l| !l.starts_with('@'))
+            .find_map(|l| STATEMENT.captures(l));
+
+        let (kind, name) = match caps {
+            Some(caps) => match (caps.get(1), caps.get(2), caps.get(3)) {
+                (Some(name), ..) => (StatementKind::Class, name.as_str()),
+                (_, Some(name), _) => (StatementKind::Def, name.as_str()),
+                // `else:` and friends look like annotations
+                (.., Some(name))
+                    if ["else", "try", "except", "finally"].contains(&name.as_str()) =>
+                {
+                    (StatementKind::Other, "")
+                }
+                (.., Some(name)) => (StatementKind::Var, name.as_str()),
+                _ => (StatementKind::Import, ""),
+            },
+            None => (StatementKind::Other, ""),
+        };
+
+        Self {
+            kind,
+            name: name.to_string(),
+            source,
+        }
+    }
+
+    /// Whether it's a docstring, `...` or `pass`, which can be left out
+    pub fn is_placeholder(&self) -> bool {
+        let source = self.source.trim();
+        self.kind == StatementKind::Other
+            && (source == "..." || source == "pass" || source.starts_with(['"', '\'']))
+    }
+
+    /// The text of the statement if it's a docstring
+    pub fn docstring(&self) -> Option<String> {
+        let source = self.source.trim();
+        if self.kind != StatementKind::Other {
+            return None;
+        }
+
+        ["\"\"\"", "\'\'\'", "\"", "\'"]
+            .into_iter()
+            .find_map(|q| source.strip_prefix(q)?.strip_suffix(q))
+            .map(|doc| {
+                let (first, rest) = doc.split_once('\n').unwrap_or((doc, ""));
+                format!("{}\n{}", first.trim(), dedent(rest))
+                    .trim()
+                    .to_string()
+            })
+    }
+
+    /// The decorators above the statement, without the `@`
+    pub fn decorators(&self) -> Vec<&str> {
+        self.source
+            .lines()
+            .take_while(|l| l.starts_with(['@', '#']))
+            .filter_map(|l| l.strip_prefix('@'))
+            .map(str::trim)
+            .collect()
+    }
+
+    /// Splits a block, like a class or an `if`, into its header and the
+    /// statements of its body. The decorators and comments above it are left
+    /// out of the header
+    pub fn split_block(&self) -> (&str, Vec<Statement>) {
+        // skip the decorators and comments
+        let start: usize = self
+            .source
+            .lines()
+            .take_while(|l| l.starts_with(['@', '#']))
+            .map(|l| l.len() + 1)
+            .sum();
+        let source = self.source.get(start..).unwrap_or_default();
+        let colon = header_end(source).unwrap_or(source.len());
+
+        let header = &source[..colon];
+        let body = source.get(colon + 1..).unwrap_or_default();
+        let (inline, body) = body.split_once('\n').unwrap_or((body, ""));
+        let body = match inline.trim() {
+            "" => dedent(body),
+            inline => inline.to_string(),
+        };
+
+        (header, split_statements(&body))
+    }
+
+    /// Splits a class into the bases written in its header, if any, and the
+    /// statements of its body
+    pub fn split_class(&self) -> (Option<Vec<String>>, Vec<Statement>) {
+        let (header, body) = self.split_block();
+        let bases = header
+            .find('(')
+            .zip(header.rfind(')'))
+            .map(|(open, close)| {
+                split_params(&header[open + 1..close])
+                    .into_iter()
+                    .map(String::from)
+                    .collect()
+            });
+
+        (bases, body)
+    }
+}
+
+/// Position of the colon that ends the header of a block, skipping the ones
+/// inside brackets, strings and comments
+fn header_end(source: &str) -> Option<usize> {
+    let source = source.as_bytes();
+    let mut depth = 0;
+    let mut i = 0;
+
+    while i < source.len() {
+        match source[i] {
+            b'(' | b'[' | b'{' => depth += 1,
+            b')' | b']' | b'}' => depth -= 1,
+            b':' if depth == 0 => return Some(i),
+            b'#' => {
+                while i + 1 < source.len() && source[i + 1] != b'\n' {
+                    i += 1;
+                }
+            }
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < source.len() && source[i] != quote {
+                    i += if source[i] == b'\\' { 2 } else { 1 };
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Removes the indentation shared by all the lines
+fn dedent(source: &str) -> String {
+    let indent = source
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.len() - l.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    source
+        .lines()
+        .map(|l| l.get(indent..).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Splits a python source into its top level statements. Blocks are kept
+/// whole, with the decorators and comments that go above them
+pub fn split_statements(source: &str) -> Vec<Statement> {
+    let mut statements = Vec::new();
+    let mut scanner = Scanner::default();
+    let mut current = String::new();
+    // whether `current` has more than decorators and comments
+    let mut started = false;
+
+    let mut finish = |current: &mut String| {
+        let source = current.trim_end().trim_start_matches('\n');
+        if !source.is_empty() {
+            statements.push(Statement::new(source.to_string()));
+        }
+        current.clear();
+    };
+
+    for line in source.lines() {
+        let top_level =
+            !scanner.continues() && !line.is_empty() && !line.starts_with(char::is_whitespace);
+        scanner.scan(line);
+
+        if top_level {
+            let above = line.starts_with('#') || line.starts_with('@');
+            if started {
+                finish(&mut current);
+            }
+            started = !above;
+        }
+
+        current.push_str(line);
+        current.push('\n');
+    }
+    finish(&mut current);
+
+    statements
+}
+
+/// A declaration that can be added to a class
+pub enum Member {
+    Method(Function),
+    Field(Var),
+}
+
+/// Splits at the commas that aren't inside brackets
+fn split_params(params: &str) -> Vec<&str> {
+    let mut split = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+
+    for (i, c) in params.char_indices() {
+        match c {
+            '[' | '(' | '{' => depth += 1,
+            ']' | ')' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                split.push(&params[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    split.push(&params[start..]);
+
+    split
+        .into_iter()
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect()
+}
+
+fn parse_param(func: Function, param: &str) -> Result<Function, String> {
+    // the default value is left out, every parameter defaults to None
+    let param = param.split_once('=').map_or(param, |(p, _)| p).trim();
+    let (name, typ) = match param.split_once(':') {
+        Some((name, typ)) => (name.trim(), Type::Primitive(typ.trim().to_string())),
+        None => (param, Type::Any),
+    };
+
+    match name {
+        "*" => Ok(func.add_star_param()),
+        // positional only parameters aren't told apart
+        "/" => Ok(func),
+        "self" | "cls" if func.parameters.is_empty() => Ok(func.add_self_param()),
+        _ => match (name.strip_prefix("**"), name.strip_prefix('*')) {
+            (Some(name), _) => Ok(func.add_keywords_param(name, typ, None)),
+            (_, Some(name)) => Ok(func.add_variadic_param(name, typ, None)),
+            _ => Ok(func.add_named_param(name, typ, false, None)),
+        },
+    }
+}
+
+/// Parses a function like `@staticmethod def new(name: str) -> Widget`. The
+/// kind is given by the decorator, or by the `self` parameter
+pub fn parse_def(source: &str) -> Result<Function, String> {
+    let mut decorators = Vec::new();
+    let mut source = source.trim();
+    while let Some(rest) = source.strip_prefix('@') {
+        let (decorator, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        decorators.push(decorator);
+        source = rest.trim_start();
+    }
+
+    let caps = DEF
+        .captures(source)
+        .ok_or_else(|| format!("expected a function definition, found `{}`", source))?;
+
+    let mut func = Function::build(&caps[1]);
+    for param in split_params(&caps[2]) {
+        func = parse_param(func, param)?;
+    }
+    if let Some(ret) = caps.get(3) {
+        func = func.returns(Type::Primitive(ret.as_str().to_string()));
+    }
+
+    let kind = match decorators.as_slice() {
+        [] if matches!(func.parameters.first(), Some(Param::Instance)) => FunctionKind::Method,
+        [] => FunctionKind::Static,
+        ["staticmethod"] => FunctionKind::StaticMethod,
+        ["classmethod"] => FunctionKind::ClassMethod,
+        _ => return Err(format!("unsupported decorators in `{}`", source)),
+    };
+
+    Ok(func.kind(kind))
+}
+
+/// Parses a method or a field like `name: str`
+pub fn parse_member(source: &str) -> Result<Member, String> {
+    let source = source.trim();
+    if source.starts_with("def ") || source.starts_with('@') {
+        let mut method = parse_def(source)?;
+        if method.kind == FunctionKind::Static {
+            method.kind = FunctionKind::StaticMethod;
+        }
+        return Ok(Member::Method(method));
+    }
+
+    let caps = FIELD
+        .captures(source)
+        .ok_or_else(|| format!("expected a method or a field, found `{}`", source))?;
+
+    Ok(Member::Field(Var {
+        name: caps[1].to_string(),
+        value: None,
+        typ: Some(Type::Primitive(caps[2].trim().to_string())),
+        doc: None,
+        deprecated: None,
+        since: None,
+        c_name: None,
+        source: None,
+        constant: false,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(func: &Function) -> Vec<String> {
+        func.parameters
+            .iter()
+            .map(|p| match p {
+                Param::Named { name, typ, .. } => format!("{}: {}", name, typ),
+                Param::Variadic { name, typ, .. } => format!("*{}: {}", name, typ),
+                Param::Keywords { name, typ, .. } => format!("**{}: {}", name, typ),
+                Param::Instance => String::from("self"),
+                Param::Star => String::from("*"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn defs() {
+        let func = parse_def("def run(self, argv: list[str], *, n: int = 0) -> int").unwrap();
+        assert_eq!(func.name, "run");
+        assert_eq!(func.kind, FunctionKind::Method);
+        assert_eq!(params(&func), ["self", "argv: list[str]", "*", "n: int"]);
+        assert_eq!(func.return_type.to_string(), "int");
+
+        let func = parse_def("def f(x, *args: str, **kwargs: int):").unwrap();
+        assert_eq!(func.kind, FunctionKind::Static);
+        assert_eq!(
+            params(&func),
+            ["x: typing.Any", "*args: str", "**kwargs: int"]
+        );
+
+        let func = parse_def("@classmethod def new(cls, d: dict[str, int]) -> W: ...").unwrap();
+        assert_eq!(func.kind, FunctionKind::ClassMethod);
+        assert_eq!(params(&func), ["self", "d: dict[str, int]"]);
+    }
+
+    #[test]
+    fn bad_defs() {
+        assert!(parse_def("run(self)").is_err());
+        assert!(parse_def("@property def x(self) -> int").is_err());
+    }
+
+    #[test]
+    fn members() {
+        match parse_member("def new(name: str) -> W").unwrap() {
+            Member::Method(m) => assert_eq!(m.kind, FunctionKind::StaticMethod),
+            Member::Field(_) => panic!("expected a method"),
+        }
+        match parse_member("name: typing.Optional[str]").unwrap() {
+            Member::Field(f) => {
+                assert_eq!(f.name, "name");
+                assert_eq!(f.typ.unwrap().to_string(), "typing.Optional[str]");
+            }
+            Member::Method(_) => panic!("expected a field"),
+        }
+        assert!(parse_member("x = 1").is_err());
+    }
+
+    #[test]
+    fn statements() {
+        let source = "import typing\n\
+                      # a comment\n\
+                      @typing.final\n\
+                      class A(B, C[int]):\n    \"\"\"doc\n\n    more\"\"\"\n    x: int\n\n    def f(self) -> None: ...\n\
+                      def g(a: dict[\n    str, int\n]) -> None: ...\n\
+                      s = \"\"\"\nnot: a statement\n\"\"\"\n\
+                      if X:\n    pass\n\
+                      else:\n    pass\n";
+        let statements = split_statements(source);
+        let kinds: Vec<_> = statements
+            .iter()
+            .map(|s| (s.kind, s.name.as_str()))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                (StatementKind::Import, ""),
+                (StatementKind::Class, "A"),
+                (StatementKind::Def, "g"),
+                (StatementKind::Var, "s"),
+                (StatementKind::Other, ""),
+                (StatementKind::Other, ""),
+            ]
+        );
+
+        let class = &statements[1];
+        assert_eq!(class.decorators(), ["typing.final"]);
+        let (bases, members) = class.split_class();
+        assert_eq!(bases.unwrap(), ["B", "C[int]"]);
+        assert_eq!(members[0].docstring().unwrap(), "doc\n\nmore");
+        assert_eq!(members[1].kind, StatementKind::Var);
+        assert_eq!(members[2].kind, StatementKind::Def);
+    }
+
+    #[test]
+    fn scanner() {
+        let mut scanner = Scanner::default();
+        scanner.scan("def f(a: str = \"(\",  # ) comment");
+        assert!(scanner.continues());
+        scanner.scan("       b: str = ')') -> None: ...");
+        assert!(!scanner.continues());
+        scanner.scan("x = '''start");
+        assert!(scanner.continues());
+        scanner.scan("end ''' + \"a \\\" b\"");
+        assert!(!scanner.continues());
+    }
+}
@@ -0,0 +1,392 @@
+//! Hand written `.pyi` fragments merged into the generated namespaces, for
+//! what can't be told with an override file, like the python helpers that
+//! PyGObject adds. `DIR/Gtk.pyi` is merged into `Gtk`
+
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use super::{
+    python::{split_statements, Statement, StatementKind},
+    Transofrmer,
+};
+use crate::{
+    declarations::{Class, FunctionKind, Namespace, Snippet, Type},
+    passes::Pass,
+};
+
+/// Marks the classes of the packs that PyGObject defines in python, which are
+/// added to the namespace instead of merged into a generated class
+const PYTHON_ONLY: &str = "# defined in python by PyGObject";
+
+/// The directory passed with `--extra-stubs`
+pub struct ExtraStubs {
+    dir: PathBuf,
+}
+
+impl From<Statement> for Snippet {
+    fn from(statement: Statement) -> Self {
+        Self {
+            name: statement.name,
+            source: statement.source,
+        }
+    }
+}
+
+/// Adds the import to the ones of the namespace, unless it's already there
+fn merge_import(ns: &mut Namespace, source: &str) {
+    let statement = source
+        .lines()
+        .filter(|l| !l.trim_start().starts_with('#'))
+        .flat_map(str::split_whitespace)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace("( ", "(")
+        .replace(", )", ")")
+        .replace(" )", ")");
+    let names = |list: &str| -> Option<Vec<String>> {
+        let list = list.trim_matches(|c| c == '(' || c == ')' || c == ' ');
+        let names = list.split(',').map(str::trim).filter(|n| !n.is_empty());
+        // renamed imports are kept as they are
+        names
+            .map(|n| (!n.contains(' ')).then(|| n.to_string()))
+            .collect()
+    };
+
+    if let Some(modules) = statement.strip_prefix("import ").and_then(names) {
+        ns.modules.extend(modules);
+    } else if let Some(imports) = statement
+        .strip_prefix("from gi.repository import ")
+        .and_then(names)
+    {
+        let own = ns.name.clone();
+        ns.imports.extend(imports.into_iter().filter(|i| *i != own));
+    } else {
+        ns.extra_imports.insert(statement);
+    }
+}
+
+/// Merges the members of the statement into the class, replacing the ones
+/// with the same name. The bases and the docstring are replaced if the
+/// statement has them
+fn merge_class(mut class: Class, statement: &Statement) -> Class {
+    let (bases, members) = statement.split_class();
+
+    if let Some(bases) = bases {
+        class.bases = bases.into_iter().map(Type::Primitive).collect();
+    }
+
+    // overloads are kept, only what was there before is replaced
+    let mut replaced = HashSet::new();
+
+    for member in members {
+        if let Some(doc) = member.docstring() {
+            class.doc = Some(doc);
+            class.raw_doc = true;
+        }
+        if member.is_placeholder() {
+            continue;
+        }
+        let name = member.name.as_str();
+        if member.kind != StatementKind::Other && replaced.insert(member.name.clone()) {
+            match member.kind {
+                StatementKind::Def => class.methods.retain(|m| match m.kind {
+                    // virtual methods have a do_ prefix
+                    FunctionKind::Virtual => name.strip_prefix("do_") != Some(&m.name),
+                    _ => m.name != name,
+                }),
+                StatementKind::Var => class.fields.retain(|f| f.name != name),
+                _ => {}
+            }
+            class.snippets.retain(|s| s.name != name);
+        }
+        class.snippets.push(member.into());
+    }
+
+    class
+}
+
+/// Merges python stubs into the namespace. Classes are merged with the
+/// generated ones, while other declarations replace the ones with the same
+/// name
+pub fn merge_stubs(ns: &mut Namespace, source: &str) -> Result<(), String> {
+    merge_statements(ns, split_statements(source))
+}
+
+/// Whether the statement is a class marked with [`PYTHON_ONLY`]
+fn is_python_only(statement: &Statement) -> bool {
+    statement.kind == StatementKind::Class
+        && statement
+            .source
+            .lines()
+            .take_while(|l| l.starts_with(['@', '#']))
+            .any(|l| l.trim() == PYTHON_ONLY)
+}
+
+/// The first of the classes that the statement refers to, if any
+fn refers_to<'a>(statement: &Statement, classes: &'a [String]) -> Option<&'a String> {
+    classes.iter().find(|class| {
+        let pattern = format!(r"(?:^|[^\w.]){}\b", regex::escape(class));
+        Regex::new(&pattern).unwrap().is_match(&statement.source)
+    })
+}
+
+/// Merges a stub pack into the namespace like [`merge_stubs`], but its
+/// classes are only merged into the generated ones, since the targeted version
+/// may not have them. The ones marked with [`PYTHON_ONLY`] are added, unless
+/// they refer to a class that was left out. Returns what was left out, and why
+pub fn merge_pack(ns: &mut Namespace, source: &str) -> Result<Vec<String>, String> {
+    let mut statements = split_statements(source);
+    let mut missing = Vec::new();
+    let mut left_out = Vec::new();
+
+    // leaving out a class may leave out what refers to it, and so on
+    loop {
+        let before = left_out.len();
+        statements.retain(|statement| {
+            let reason = match statement.kind {
+                StatementKind::Import => None,
+                StatementKind::Class if !is_python_only(statement) => {
+                    let found = ns.classes.contains(statement.name.as_str());
+                    (!found).then(|| String::from("no such class"))
+                }
+                _ => refers_to(statement, &missing).map(|class| format!("refers to {}", class)),
+            };
+            match reason {
+                Some(reason) => {
+                    left_out.push(format!("{}: {}", statement.name, reason));
+                    missing.push(statement.name.clone());
+                    false
+                }
+                None => true,
+            }
+        });
+        if left_out.len() == before {
+            break;
+        }
+    }
+
+    merge_statements(ns, statements)?;
+    Ok(left_out)
+}
+
+fn merge_statements(ns: &mut Namespace, statements: Vec<Statement>) -> Result<(), String> {
+    let mut replaced = HashSet::new();
+
+    for statement in statements.into_iter().filter(|s| !s.is_placeholder()) {
+        let name = statement.name.as_str();
+        match statement.kind {
+            StatementKind::Import => merge_import(ns, &statement.source),
+            StatementKind::Class if ns.classes.contains(name) => {
+                Transofrmer(ns).transform_class(name, |c| Ok(merge_class(c, &statement)))?
+            }
+            kind => {
+                if kind != StatementKind::Other && replaced.insert(statement.name.clone()) {
+                    match kind {
+                        StatementKind::Class => ns.enums.retain(|e| e.name != name),
+                        StatementKind::Def => ns.functions.retain(|f| f.name != name),
+                        StatementKind::Var => ns.constants.retain(|c| c.name != name),
+                        _ => {}
+                    }
+                    ns.snippets.retain(|s| s.name != name);
+                }
+                ns.snippets.push(statement.into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+impl ExtraStubs {
+    pub fn new(dir: &Path) -> Result<Self, String> {
+        if !dir.is_dir() {
+            return Err(format!("{}: not a directory", dir.display()));
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    /// Merges the stubs of the namespace, if there are any
+    pub fn apply(&self, ns: &mut Namespace) -> Result<(), String> {
+        let path = self.dir.join(format!("{}.pyi", ns.name));
+        if !path.exists() {
+            return Ok(());
+        }
+        let source = fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+
+        merge_stubs(ns, &source).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+}
+
+impl Pass for ExtraStubs {
+    fn namespace(&mut self, ns: &mut Namespace) -> Result<(), String> {
+        self.apply(ns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::analyze_gir;
+
+    const GIR: &str = r#"<repository><namespace name="Test" version="1.0">
+        <constant name="MAX" value="10"><type name="gint"/></constant>
+        <function name="init">
+            <return-value><type name="none"/></return-value>
+        </function>
+        <class name="Widget">
+            <doc>A widget.</doc>
+            <field name="width"><type name="gint"/></field>
+            <method name="show">
+                <return-value><type name="none"/></return-value>
+                <parameters>
+                    <instance-parameter name="self"><type name="Widget"/></instance-parameter>
+                </parameters>
+            </method>
+            <method name="hide">
+                <return-value><type name="none"/></return-value>
+                <parameters>
+                    <instance-parameter name="self"><type name="Widget"/></instance-parameter>
+                </parameters>
+            </method>
+        </class>
+        <class name="Button" parent="Widget"/>
+    </namespace></repository>"#;
+
+    fn merged(source: &str) -> Namespace {
+        let mut ns = analyze_gir(GIR);
+        merge_stubs(&mut ns, source).unwrap();
+        ns
+    }
+
+    fn snippets(snippets: &[Snippet]) -> Vec<&str> {
+        snippets.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn members() {
+        let ns = merged(
+            "class Widget:\n    \
+                 width: float\n    \
+                 @typing.overload\n    \
+                 def show(self) -> None: ...\n    \
+                 @typing.overload\n    \
+                 def show(self, animate: bool) -> None: ...\n    \
+                 def __len__(self) -> int: ...\n",
+        );
+
+        let widget = ns.classes.get("Widget").unwrap();
+        // both overloads replace the generated method, the rest is kept
+        assert!(widget.find_method("show").is_none());
+        assert!(widget.find_method("hide").is_some());
+        assert!(widget.fields.is_empty());
+        assert_eq!(
+            snippets(&widget.snippets),
+            ["width", "show", "show", "__len__"]
+        );
+        // there are no bases in the header, so they're kept
+        assert_eq!(
+            ns.classes.get("Button").unwrap().bases[0].to_string(),
+            "Widget"
+        );
+    }
+
+    #[test]
+    fn bases() {
+        let ns = merged("class Button(Widget, typing.Generic[T]): ...\n");
+
+        let button = ns.classes.get("Button").unwrap();
+        let bases: Vec<_> = button.bases.iter().map(|b| b.to_string()).collect();
+        assert_eq!(bases, ["Widget", "typing.Generic[T]"]);
+        assert!(button.snippets.is_empty());
+    }
+
+    #[test]
+    fn docstrings() {
+        let ns = merged("class Button:\n    def click(self) -> None: ...\n");
+        let button = ns.classes.get("Button").unwrap();
+        assert_eq!(button.doc, None);
+
+        let ns = merged("class Widget:\n    def click(self) -> None: ...\n");
+        let widget = ns.classes.get("Widget").unwrap();
+        assert_eq!(widget.doc.as_deref(), Some("A widget."));
+        assert!(!widget.raw_doc);
+
+        let ns = merged("class Widget:\n    \"\"\"Shows `things`\n\n    in python\n    \"\"\"\n");
+        let widget = ns.classes.get("Widget").unwrap();
+        assert_eq!(widget.doc.as_deref(), Some("Shows `things`\n\nin python"));
+        assert!(widget.raw_doc);
+    }
+
+    #[test]
+    fn imports() {
+        let mut ns = analyze_gir(GIR);
+        ns.modules.insert(String::from("enum"));
+        merge_stubs(
+            &mut ns,
+            "import typing, cairo\nfrom gi.repository import GLib, Test",
+        )
+        .unwrap();
+        merge_stubs(
+            &mut ns,
+            "import cairo\nfrom gi.repository import (\n    GLib,\n    Gio,\n)\n\
+             from collections.abc import Iterator\nimport numpy as np\n",
+        )
+        .unwrap();
+
+        let mut modules: Vec<_> = ns.modules.iter().map(String::as_str).collect();
+        modules.sort_unstable();
+        assert_eq!(modules, ["cairo", "enum", "typing"]);
+        let mut imports: Vec<_> = ns.imports.iter().map(String::as_str).collect();
+        imports.sort_unstable();
+        assert_eq!(imports, ["GLib", "Gio"]);
+        let extra: Vec<_> = ns.extra_imports.iter().map(String::as_str).collect();
+        assert_eq!(
+            extra,
+            ["from collections.abc import Iterator", "import numpy as np"]
+        );
+    }
+
+    #[test]
+    fn other_declarations() {
+        let ns = merged("MAX: int\ndef init(argv: list[str]) -> None: ...\nclass Other: ...\n");
+
+        assert!(ns.constants.is_empty());
+        assert!(ns.functions.is_empty());
+        assert_eq!(snippets(&ns.snippets), ["MAX", "init", "Other"]);
+    }
+
+    #[test]
+    fn packs() {
+        let mut ns = analyze_gir(GIR);
+        let left_out = merge_pack(
+            &mut ns,
+            "class Widget:\n    def __len__(self) -> int: ...\n\
+             class Missing:\n    def f(self) -> None: ...\n\
+             # defined in python by PyGObject\n\
+             class Helper:\n    x: int\n\
+             # defined in python by PyGObject\n\
+             class Row:\n    missing: Missing\n\
+             _Key = typing.Union[Row, int]\n\
+             def other(w: Test.Missing) -> None: ...\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            left_out,
+            [
+                "Missing: no such class",
+                "Row: refers to Missing",
+                "_Key: refers to Row",
+            ]
+        );
+        assert!(!ns.classes.get("Widget").unwrap().snippets.is_empty());
+        assert_eq!(snippets(&ns.snippets), ["Helper", "other"]);
+    }
+}
@@ -0,0 +1,86 @@
+//! Writes the `gi` package around the generated namespaces: the hand made
+//! stubs of its python modules, and the overloads of `require_version` for the
+//! namespaces with GIR files
+
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::generation::quote;
+
+/// The hand made stubs, by their path in the package
+const MODULES: &[(&str, &str)] = &[
+    ("__init__.pyi", include_str!("gi_init.pyi")),
+    ("repository/__init__.pyi", include_str!("gi_repository.pyi")),
+    ("events.pyi", include_str!("gi_events.pyi")),
+    ("types.pyi", include_str!("gi_types.pyi")),
+    ("module.pyi", include_str!("gi_module.pyi")),
+    ("overrides/__init__.pyi", include_str!("gi_overrides.pyi")),
+];
+
+/// Writes `require_version`, with an overload for each namespace that tells
+/// the versions apart. With `loose`, or without GIR files, the last one takes
+/// any namespace, for typelibs without GIR files. It matches the wrong
+/// versions of the others too, so they aren't caught anymore. Single overloads
+/// are errors, so they're written as plain functions
+fn write_require_version(
+    writer: &mut impl Write,
+    installed: &BTreeMap<String, Vec<String>>,
+    loose: bool,
+) -> io::Result<()> {
+    let mut signatures: Vec<_> = installed
+        .iter()
+        .map(|(namespace, versions)| {
+            let versions: Vec<_> = versions.iter().map(|v| quote(v)).collect();
+            format!(
+                "def require_version(namespace: Literal[{}], version: Literal[{}]) -> None: ...",
+                quote(namespace),
+                versions.join(", "),
+            )
+        })
+        .collect();
+    if loose || installed.is_empty() {
+        signatures.push(String::from(
+            "def require_version(namespace: str, version: str) -> None:\n    \
+             \"\"\"Takes any namespace, for typelibs without a GIR file. The versions \
+             aren't checked, not even the ones of the namespaces above\"\"\"",
+        ));
+    }
+
+    writeln!(writer)?;
+    for signature in &signatures {
+        // a single overload is an error
+        if signatures.len() > 1 {
+            writeln!(writer, "@overload")?;
+        }
+        writeln!(writer, "{}", signature)?;
+    }
+    Ok(())
+}
+
+/// Creates the package in `dir`, the generated namespaces go in
+/// `dir/repository`. `loose` lets `require_version` take any namespace
+pub fn create_stub_tree(
+    dir: &Path,
+    installed: &BTreeMap<String, Vec<String>>,
+    loose: bool,
+) -> io::Result<()> {
+    fs::create_dir_all(dir.join("repository"))?;
+    fs::create_dir_all(dir.join("overrides"))?;
+
+    fs::write(dir.join("py.typed"), "partial\n")?;
+
+    for (path, source) in MODULES {
+        let mut writer = BufWriter::new(File::create(dir.join(path))?);
+        writer.write_all(source.as_bytes())?;
+        if *path == "__init__.pyi" {
+            write_require_version(&mut writer, installed, loose)?;
+        }
+        writer.flush()?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! Transformations of the declarations, run on every namespace after it's
+//! analyzed and before it's written. A pass overrides the methods for the
+//! declarations it cares about, and the rest go down to the ones inside:
+//!
+//! ```
+//! use gengir::{
+//!     declarations::{Function, FunctionKind, Type},
+//!     passes::{Pass, Pipeline, Stage},
+//! };
+//!
+//! struct RefReturnsSelf;
+//!
+//! impl Pass for RefReturnsSelf {
+//!     fn function(&mut self, func: &mut Function) -> bool {
+//!         if func.kind == FunctionKind::Method && func.name.ends_with("_ref") {
+//!             func.return_type = Type::Instance;
+//!         }
+//!         true
+//!     }
+//! }
+//!
+//! let mut pipeline = Pipeline::default();
+//! pipeline.add(Stage::User, RefReturnsSelf);
+//! ```
+
+use std::mem;
+
+use crate::declarations::{Class, Enumeration, Function, Namespace, Param, Var};
+
+/// A transformation of the declarations. By default, every method only visits
+/// the declarations inside, which are left out if the methods that visit them
+/// return `false`. The snippets of the extra stubs aren't visited
+pub trait Pass {
+    fn namespace(&mut self, ns: &mut Namespace) -> Result<(), String> {
+        walk_namespace(self, ns);
+        Ok(())
+    }
+
+    fn class(&mut self, class: &mut Class) -> bool {
+        walk_class(self, class);
+        true
+    }
+
+    fn enumeration(&mut self, enumeration: &mut Enumeration) -> bool {
+        walk_enumeration(self, enumeration);
+        true
+    }
+
+    /// Visits constants, enum members and the fields of classes
+    fn var(&mut self, _var: &mut Var) -> bool {
+        true
+    }
+
+    /// Visits functions, methods, virtual methods and constructors. The
+    /// constructors are kept anyways
+    fn function(&mut self, func: &mut Function) -> bool {
+        walk_function(self, func);
+        true
+    }
+
+    fn param(&mut self, _param: &mut Param) {}
+}
+
+/// Visits the constants, enums, functions and classes of the namespace
+pub fn walk_namespace<P: Pass + ?Sized>(pass: &mut P, ns: &mut Namespace) {
+    ns.constants.retain_mut(|c| pass.var(c));
+    ns.enums.retain_mut(|e| pass.enumeration(e));
+    ns.functions.retain_mut(|f| pass.function(f));
+    // renamed classes have to be rehashed
+    ns.classes = mem::take(&mut ns.classes)
+        .into_iter()
+        .filter_map(|mut c| pass.class(&mut c).then_some(c))
+        .collect();
+}
+
+/// Visits the fields, the constructor and the methods of the class
+pub fn walk_class<P: Pass + ?Sized>(pass: &mut P, class: &mut Class) {
+    class.fields.retain_mut(|f| pass.var(f));
+    pass.function(&mut class.constructor);
+    class.methods = mem::take(&mut class.methods)
+        .into_iter()
+        .filter_map(|mut m| pass.function(&mut m).then_some(m))
+        .collect();
+}
+
+/// Visits the members of the enum
+pub fn walk_enumeration<P: Pass + ?Sized>(pass: &mut P, enumeration: &mut Enumeration) {
+    enumeration.values.retain_mut(|v| pass.var(v));
+}
+
+/// Visits the parameters of the function
+pub fn walk_function<P: Pass + ?Sized>(pass: &mut P, func: &mut Function) {
+    for param in &mut func.parameters {
+        pass.param(param);
+    }
+}
+
+/// When a pass runs. Passes of the same stage run in the order they're added
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    /// points types to what's available at runtime, like the pycairo classes
+    Resolution,
+    /// the built-in overrides, then the override files and the extra stubs
+    Overrides,
+    /// passes written for a project
+    User,
+    /// leaves out what isn't available in the targeted versions
+    Filtering,
+}
+
+/// Runs passes on namespaces, ordered by [`Stage`]
+#[derive(Default)]
+pub struct Pipeline<'a> {
+    passes: Vec<(Stage, Box<dyn Pass + 'a>)>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub fn add(&mut self, stage: Stage, pass: impl Pass + 'a) -> &mut Self {
+        // after the other passes of the stage
+        let index = self.passes.partition_point(|(s, _)| *s <= stage);
+        self.passes.insert(index, (stage, Box::new(pass)));
+        self
+    }
+
+    /// Runs every pass on the namespace, stopping at the first error
+    pub fn run(&mut self, ns: &mut Namespace) -> Result<(), String> {
+        for (_, pass) in &mut self.passes {
+            pass.namespace(ns)?;
+        }
+        Ok(())
+    }
+}